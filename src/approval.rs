@@ -0,0 +1,34 @@
+use anyhow::{anyhow, Result};
+use std::io::{self, IsTerminal, Write};
+use text_io::read;
+
+use crate::config::Settings;
+
+// environments with require-approval ask before ht changes their org, without a terminal
+// the approval has to be given with --approve
+pub fn check(settings: &Settings, action: &str, target_org: &str, approved: bool) -> Result<()> {
+    if !settings.require_approval.value || approved {
+        return Ok(());
+    }
+    let environment = settings.environment.as_deref().unwrap_or(target_org);
+    if !io::stdin().is_terminal() {
+        return Err(anyhow!(
+            "{} requires approval to {} {}, pass --approve",
+            environment,
+            action,
+            target_org
+        ));
+    }
+
+    print!(
+        "{} requires approval, {} {}? [y/N] ",
+        environment, action, target_org
+    );
+    io::stdout().flush()?;
+    let answer: String = read!("{}\n");
+    if answer.trim().eq_ignore_ascii_case("y") {
+        Ok(())
+    } else {
+        Err(anyhow!("{} was not approved", action))
+    }
+}
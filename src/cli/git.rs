@@ -1,3 +1,5 @@
+#![allow(dead_code)]
+
 use anyhow::Result;
 use git2::{Index, IndexAddOption, Oid, Repository};
 
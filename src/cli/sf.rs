@@ -7,7 +7,11 @@ use cli_table::print_stdout;
 use cli_table::{Cell, Style, Table, TableStruct};
 use enum_as_inner::EnumAsInner;
 use indicatif::ProgressBar;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
 use std::process::Stdio;
 use std::time::Duration;
 use std::{
@@ -15,8 +19,13 @@ use std::{
     process::Command,
 };
 
+use crate::github::{self, Annotation};
+use crate::project;
+
 pub trait SfCliResult {
     fn get_formatted_results(&self) -> TableStruct;
+    fn get_annotations(&self) -> Vec<Annotation>;
+    fn get_markdown_summary(&self) -> String;
 }
 
 #[derive(Deserialize, Debug)]
@@ -49,14 +58,123 @@ pub struct RunTestSummary {
 struct MetadataComponent {
     component_type: String,
     full_name: String,
+    file_name: Option<String>,
+    line_number: Option<u32>,
+    column_number: Option<u32>,
     problem: Option<String>,
+    problem_type: Option<String>,
     success: bool,
 }
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
+pub struct DeployedFile {
+    full_name: String,
+    #[serde(rename = "type")]
+    component_type: String,
+    state: String,
+    file_path: Option<String>,
+}
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
 pub struct DeployDetails {
+    #[serde(default, deserialize_with = "one_or_many")]
     component_successes: Vec<MetadataComponent>,
+    #[serde(default, deserialize_with = "one_or_many")]
     component_failures: Vec<MetadataComponent>,
+    run_test_result: Option<DeployTestResult>,
+}
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DeployTestResult {
+    #[serde(default, deserialize_with = "one_or_many")]
+    code_coverage: Vec<CodeCoverage>,
+}
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct CodeCoverage {
+    num_locations: u32,
+    num_locations_not_covered: u32,
+}
+
+impl DeployDetails {
+    fn get_success_count_by_type(&self) -> BTreeMap<String, usize> {
+        let mut count_by_type = BTreeMap::new();
+        for component in &self.component_successes {
+            // the generated package.xml is reported as a success without a type
+            if !component.component_type.is_empty() {
+                *count_by_type
+                    .entry(component.component_type.clone())
+                    .or_insert(0) += 1;
+            }
+        }
+        count_by_type
+    }
+
+    fn get_failure_results(&self, files: &[DeployedFile]) -> TableStruct {
+        self.component_failures
+            .iter()
+            .map(|x| {
+                vec![
+                    x.component_type.clone().cell(),
+                    x.full_name.clone().cell(),
+                    get_source_path(files, x)
+                        .or(x.file_name.clone())
+                        .unwrap_or_default()
+                        .cell(),
+                    x.line_number
+                        .map(|line| line.to_string())
+                        .unwrap_or_default()
+                        .cell()
+                        .justify(Justify::Right),
+                    x.column_number
+                        .map(|column| column.to_string())
+                        .unwrap_or_default()
+                        .cell()
+                        .justify(Justify::Right),
+                    x.problem_type.clone().unwrap_or_default().cell(),
+                    x.problem.clone().unwrap_or_default().cell(),
+                ]
+            })
+            .collect::<Vec<_>>()
+            .table()
+            .title(vec![
+                "Type".cell().bold(true),
+                "Name".cell().bold(true),
+                "File".cell().bold(true),
+                "Line".cell().bold(true),
+                "Column".cell().bold(true),
+                "Problem Type".cell().bold(true),
+                "Problem".cell().bold(true),
+            ])
+    }
+}
+
+// the metadata api returns a single object instead of an array when there is one component
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        One(T),
+        Many(Vec<T>),
+    }
+
+    Ok(match OneOrMany::<T>::deserialize(deserializer)? {
+        OneOrMany::One(x) => vec![x],
+        OneOrMany::Many(x) => x,
+    })
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedRecord {
+    ref_id: String,
+    #[serde(rename = "type")]
+    sobject_type: String,
+    id: String,
 }
 
 #[derive(Deserialize, Debug)]
@@ -77,6 +195,14 @@ pub enum CliResult {
         #[serde(rename = "orgId")]
         org_id: String,
     },
+    #[serde(rename_all = "camelCase")]
+    DisplayUser {
+        username: String,
+        profile_name: String,
+        id: String,
+        instance_url: String,
+        password: Option<String>,
+    },
     Authorization {
         username: String,
         #[serde(rename = "instanceUrl")]
@@ -84,6 +210,10 @@ pub enum CliResult {
     },
     ProjectDeploy {
         details: DeployDetails,
+        #[serde(default)]
+        files: Vec<DeployedFile>,
+        #[serde(default)]
+        id: String,
     },
     #[serde(rename_all = "camelCase")]
     ExecuteAnonymousApex {
@@ -91,6 +221,19 @@ pub enum CliResult {
         compile_problem: String,
         exception_message: String,
         exception_stack_trace: String,
+        #[serde(default)]
+        compiled: bool,
+        #[serde(default)]
+        line: i32,
+        #[serde(default)]
+        column: i32,
+        logs: Option<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    OpenOrg {
+        url: String,
+        username: String,
+        org_id: String,
     },
     DeleteScratchOrg {
         username: String,
@@ -101,10 +244,47 @@ pub enum CliResult {
         summary: RunTestSummary,
         tests: Vec<RunTestResult>,
     },
+    #[serde(rename_all = "camelCase")]
+    Query {
+        total_size: u32,
+        records: Vec<Value>,
+    },
+    DataImport(Vec<ImportedRecord>),
+    // an empty list is read as an empty DataImport
+    PackageVersionList(Vec<PackageVersionInfo>),
+    PackageList(Vec<PackageInfo>),
+    CreatePackageVersion {
+        #[serde(rename = "Status")]
+        status: String,
+        #[serde(rename = "Package2Id")]
+        package_id: String,
+        #[serde(rename = "Package2VersionId")]
+        package_version_id: Option<String>,
+        #[serde(rename = "SubscriberPackageVersionId")]
+        subscriber_package_version_id: Option<String>,
+    },
     PackageInstall {
         #[serde(rename = "Status")]
         status: String,
     },
+    #[serde(rename_all = "camelCase")]
+    CreateUser {
+        org_id: String,
+        fields: BTreeMap<String, Value>,
+    },
+    #[serde(rename_all = "camelCase")]
+    OrgList {
+        scratch_orgs: Vec<LocalOrg>,
+        non_scratch_orgs: Vec<LocalOrg>,
+    },
+    DeleteRecord {
+        id: String,
+        success: bool,
+    },
+    AssignPermissions {
+        successes: Vec<PermissionAssignment>,
+        failures: Vec<PermissionAssignmentFailure>,
+    },
 }
 impl SfCliResult for SfCliCommandOutput {
     fn get_formatted_results(&self) -> TableStruct {
@@ -161,38 +341,56 @@ impl SfCliResult for SfCliCommandOutput {
                 "".cell(),
             ])
             .bold(true),
-            CliResult::ProjectDeploy { details } => vec![
-                vec![
+            CliResult::ProjectDeploy { details, .. } => {
+                let mut rows = vec![vec![
                     "Is Successful".cell(),
-                    (!details.component_failures.is_empty())
+                    (self.status == 0 && details.component_failures.is_empty())
                         .cell()
                         .justify(Justify::Right),
-                ],
-                vec![
-                    "Problems".cell(),
+                ]];
+                for (component_type, count) in details.get_success_count_by_type() {
+                    rows.push(vec![
+                        component_type.cell(),
+                        count.cell().justify(Justify::Right),
+                    ]);
+                }
+                rows.push(vec![
+                    "Failures".cell(),
                     details
                         .component_failures
-                        .iter()
-                        .map(|x| x.problem.as_ref().unwrap().as_str())
-                        .collect::<Vec<_>>()
-                        .join("\n")
+                        .len()
                         .cell()
                         .justify(Justify::Right),
-                ],
-            ]
-            .table()
-            .title(vec!["Project Deploy Results".cell().bold(true), "".cell()])
-            .bold(true),
+                ]);
+                rows.table()
+                    .title(vec!["Project Deploy Results".cell().bold(true), "".cell()])
+                    .bold(true)
+            }
             CliResult::ExecuteAnonymousApex {
-                compile_problem, ..
+                success,
+                compile_problem,
+                exception_message,
+                line,
+                column,
+                ..
             } => vec![
                 vec![
                     "Compilation Successful".cell(),
                     compile_problem.is_empty().cell().justify(Justify::Right),
                 ],
+                vec![
+                    "Execution Successful".cell(),
+                    success.cell().justify(Justify::Right),
+                ],
                 vec![
                     "Problems".cell(),
-                    compile_problem.cell().justify(Justify::Right),
+                    if !compile_problem.is_empty() {
+                        format!("line {}, column {}: {}", line, column, compile_problem)
+                    } else {
+                        exception_message.clone()
+                    }
+                    .cell()
+                    .justify(Justify::Right),
                 ],
             ]
             .table()
@@ -239,12 +437,213 @@ impl SfCliResult for SfCliCommandOutput {
             .table()
             .title(vec!["Run Apex Tests Result".cell().bold(true), "".cell()])
             .bold(true),
+            CliResult::DataImport(records) => {
+                let mut count_by_type: BTreeMap<&str, usize> = BTreeMap::new();
+                for record in records {
+                    *count_by_type
+                        .entry(record.sobject_type.as_str())
+                        .or_insert(0) += 1;
+                }
+                count_by_type
+                    .into_iter()
+                    .map(|(sobject_type, count)| {
+                        vec![sobject_type.cell(), count.cell().justify(Justify::Right)]
+                    })
+                    .collect::<Vec<_>>()
+                    .table()
+                    .title(vec!["Data Import Results".cell().bold(true), "".cell()])
+                    .bold(true)
+            }
             _ => unreachable!(),
         }
     }
+
+    fn get_annotations(&self) -> Vec<Annotation> {
+        match &self.result {
+            Some(CliResult::ProjectDeploy { details, files, .. }) => details
+                .component_failures
+                .iter()
+                .map(|x| Annotation {
+                    file: get_source_path(files, x).or(x.file_name.clone()),
+                    line: x.line_number,
+                    column: x.column_number,
+                    title: Some(format!("{} {}", x.component_type, x.full_name)),
+                    message: x.problem.clone().unwrap_or_default(),
+                })
+                .collect(),
+            Some(CliResult::RunApexTests { tests, .. }) => {
+                let failures = tests
+                    .iter()
+                    .filter(|x| x.outcome == "Fail")
+                    .collect::<Vec<_>>();
+                if failures.is_empty() {
+                    return Vec::new();
+                }
+                let source_files = project::index_source_files();
+                failures
+                    .into_iter()
+                    .map(|x| {
+                        let mut annotation =
+                            Annotation::new(&x.message.clone().unwrap_or_default());
+                        annotation.title = Some(x.full_name.clone());
+                        if let Some((file, line, column)) = x
+                            .stack_trace
+                            .as_deref()
+                            .and_then(|x| get_stack_trace_location(x, &source_files))
+                        {
+                            annotation.file = Some(file);
+                            annotation.line = Some(line);
+                            annotation.column = Some(column);
+                        }
+                        annotation
+                    })
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn get_markdown_summary(&self) -> String {
+        match &self.result {
+            Some(CliResult::ProjectDeploy { details, files, .. }) => {
+                if details.component_failures.is_empty() {
+                    format!(
+                        "### Project Deploy Results\n\nDeployed {} components successfully\n",
+                        details.component_successes.len()
+                    )
+                } else {
+                    format!(
+                        "### Project Deploy Results\n\n{}",
+                        github::markdown_table(
+                            &["Type", "Name", "File", "Line", "Problem Type", "Problem"],
+                            details
+                                .component_failures
+                                .iter()
+                                .map(|x| {
+                                    vec![
+                                        x.component_type.clone(),
+                                        x.full_name.clone(),
+                                        get_source_path(files, x)
+                                            .or(x.file_name.clone())
+                                            .unwrap_or_default(),
+                                        x.line_number
+                                            .map(|line| line.to_string())
+                                            .unwrap_or_default(),
+                                        x.problem_type.clone().unwrap_or_default(),
+                                        x.problem.clone().unwrap_or_default(),
+                                    ]
+                                })
+                                .collect(),
+                        )
+                    )
+                }
+            }
+            Some(CliResult::RunApexTests { summary, tests }) => {
+                let mut markdown = format!(
+                    "### Run Apex Tests Results\n\n{}",
+                    github::markdown_table(
+                        &["Tests Ran", "Failing", "Code Coverage"],
+                        vec![vec![
+                            summary.tests_ran.to_string(),
+                            summary.failing.to_string(),
+                            summary.org_wide_coverage.clone(),
+                        ]],
+                    )
+                );
+                let failures = tests
+                    .iter()
+                    .filter(|x| x.outcome == "Fail")
+                    .map(|x| {
+                        vec![
+                            x.full_name.clone(),
+                            x.message.clone().unwrap_or_default(),
+                            x.stack_trace.clone().unwrap_or_default(),
+                        ]
+                    })
+                    .collect::<Vec<_>>();
+                if !failures.is_empty() {
+                    markdown.push('\n');
+                    markdown.push_str(&github::markdown_table(
+                        &["Test", "Message", "Stack Trace"],
+                        failures,
+                    ));
+                }
+                markdown
+            }
+            _ => String::new(),
+        }
+    }
+}
+
+fn get_source_path(files: &[DeployedFile], component: &MetadataComponent) -> Option<String> {
+    files
+        .iter()
+        .filter(|x| {
+            x.full_name == component.full_name && x.component_type == component.component_type
+        })
+        .filter_map(|x| x.file_path.clone())
+        .find(|x| !x.ends_with("-meta.xml"))
+}
+
+// stack traces look like "Class.TestClass.runTest: line 10, column 1"
+fn get_stack_trace_location(
+    stack_trace: &str,
+    source_files: &BTreeMap<String, String>,
+) -> Option<(String, u32, u32)> {
+    let first_frame = stack_trace.lines().next()?;
+    let (frame, location) = first_frame.split_once(": ")?;
+    let mut numbers = location
+        .split(',')
+        .filter_map(|x| x.split_whitespace().last())
+        .map(|x| x.parse::<u32>());
+    let line = numbers.next()?.ok()?;
+    let column = numbers.next()?.ok()?;
+
+    let segments = frame.strip_prefix("Class.")?.split('.').collect::<Vec<_>>();
+    let file = segments[..segments.len() - 1]
+        .iter()
+        .find_map(|x| source_files.get(&format!("{}.cls", x)))?;
+    Some((file.clone(), line, column))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LocalOrg {
+    pub alias: Option<String>,
+    pub username: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct PackageInfo {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct PackageVersionInfo {
+    pub package2_id: String,
+    pub package2_name: String,
+    // major.minor.patch.build
+    pub version: String,
+    pub subscriber_package_version_id: String,
+    pub is_released: bool,
+    #[serde(default)]
+    pub validation_skipped: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PermissionAssignment {
+    name: String,
+    value: String,
+}
+#[derive(Deserialize, Debug)]
+pub struct PermissionAssignmentFailure {
+    name: String,
+    message: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Debug)]
 pub struct SfCliCommandOutput {
     name: Option<String>,
     message: Option<String>,
@@ -252,6 +651,28 @@ pub struct SfCliCommandOutput {
     status: u32,
 }
 
+impl SfCliCommandOutput {
+    // org wide coverage of a test run, or the coverage of the classes a deployment tested
+    pub fn get_coverage(&self) -> Option<f64> {
+        match self.result.as_ref()? {
+            CliResult::RunApexTests { summary, .. } => {
+                summary.org_wide_coverage.trim_end_matches('%').parse().ok()
+            }
+            CliResult::ProjectDeploy { details, .. } => {
+                let coverage = &details.run_test_result.as_ref()?.code_coverage;
+                let locations = coverage.iter().map(|x| x.num_locations).sum::<u32>();
+                let not_covered = coverage
+                    .iter()
+                    .map(|x| x.num_locations_not_covered)
+                    .sum::<u32>();
+                (locations > 0)
+                    .then(|| f64::from(locations - not_covered) * 100.0 / f64::from(locations))
+            }
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct SfCliError;
 
@@ -268,10 +689,25 @@ pub fn verify_cli_is_installed() -> Result<()> {
     }
 }
 
+// the description of the scratch orgs ht creates, followed by their alias, so they can be found
+// on the Dev Hub from any machine
+pub const SCRATCH_ORG_DESCRIPTION: &str = "Created by ht as ";
+
+#[derive(Debug, Clone)]
+pub struct ScratchOrgOptions {
+    // verify falls back to the definitionFile of the default package
+    pub definition_file: Option<String>,
+    pub duration_days: Option<u32>,
+    pub edition: Option<String>,
+    pub no_namespace: bool,
+}
+
 #[derive(Clone)]
 pub struct SalesforceCli {
     output: String,
     target_org: String,
+    // minutes sf waits for deployments, installs and test runs
+    wait_minutes: u64,
     progress_bar: ProgressBar,
 }
 impl SalesforceCli {
@@ -279,35 +715,63 @@ impl SalesforceCli {
         SalesforceCli {
             output: String::new(),
             target_org: target_org.unwrap_or(String::from("")),
+            wait_minutes: 60,
             progress_bar: ProgressBar::new_spinner(),
         }
     }
 
+    pub fn set_wait_minutes(&mut self, minutes: u64) -> &mut Self {
+        self.wait_minutes = minutes;
+        self
+    }
+
     fn mock_cli_output(&mut self, output: String) -> &mut Self {
         self.output = output;
         self
     }
 
-    pub fn create_scratch_org(&mut self, devhub: &str) -> Result<SfCliCommandOutput> {
+    pub fn create_scratch_org(
+        &mut self,
+        devhub: &str,
+        options: &ScratchOrgOptions,
+    ) -> Result<SfCliCommandOutput> {
         self.progress_bar
             .to_owned()
             .with_message("Creating scratch org")
             .enable_steady_tick(Duration::from_millis(120));
         let output = if self.output.is_empty() {
             let target_org = self.target_org.clone();
-            self.get_output(vec![
+            let description = format!("{}{}", SCRATCH_ORG_DESCRIPTION, target_org);
+            let duration_days = options.duration_days.map(|x| x.to_string());
+            let mut args = vec![
                 "org",
                 "create",
                 "scratch",
                 "-v",
                 devhub,
-                "--definition-file",
-                "config/project-scratch-def.json",
                 "--alias",
                 target_org.as_str(),
+                "--description",
+                description.as_str(),
                 "--set-default",
                 "--json",
-            ])?
+            ];
+            if let Some(definition_file) = &options.definition_file {
+                args.push("--definition-file");
+                args.push(definition_file.as_str());
+            }
+            if let Some(duration_days) = &duration_days {
+                args.push("--duration-days");
+                args.push(duration_days.as_str());
+            }
+            if let Some(edition) = &options.edition {
+                args.push("--edition");
+                args.push(edition.as_str());
+            }
+            if options.no_namespace {
+                args.push("--no-namespace");
+            }
+            self.get_output(args)?
         } else {
             self.output.clone()
         };
@@ -338,13 +802,24 @@ impl SalesforceCli {
         }
     }
 
+    // waits for the build, without -w sf returns before the version has a subscriber package version id
     pub fn create_package_version(&mut self, devhub: &str) -> Result<SfCliCommandOutput> {
         self.progress_bar
             .to_owned()
             .with_message("Creating package version")
             .enable_steady_tick(Duration::from_millis(120));
         let output = if self.output.is_empty() {
-            self.get_output(vec!["package", "version", "create", "-v", devhub, "--json"])?
+            let wait_minutes = self.wait_minutes.to_string();
+            self.get_output(vec![
+                "package",
+                "version",
+                "create",
+                "-v",
+                devhub,
+                "-w",
+                wait_minutes.as_str(),
+                "--json",
+            ])?
         } else {
             self.output.clone()
         };
@@ -353,7 +828,7 @@ impl SalesforceCli {
             .expect("could not deserialize sf cli command output");
         if command_output.status != 0 {
             return Err(anyhow!(SfCliError).context(format!(
-                "could not create scratch org: {}",
+                "could not create package version: {}",
                 command_output.message.unwrap(),
             )));
         }
@@ -394,6 +869,33 @@ impl SalesforceCli {
         Ok(command_output)
     }
 
+    // the url logs in through frontdoor.jsp without a password
+    pub fn open_org(&mut self) -> Result<SfCliCommandOutput> {
+        let output = if self.output.is_empty() {
+            let target_org = self.target_org.clone();
+            self.get_output(vec![
+                "org",
+                "open",
+                "--url-only",
+                "--json",
+                "-o",
+                target_org.as_str(),
+            ])?
+        } else {
+            self.output.clone()
+        };
+
+        let command_output: SfCliCommandOutput = serde_json::from_str(output.as_str())
+            .expect("could not deserialize sf cli command output");
+        if command_output.status != 0 {
+            return Err(anyhow!(SfCliError).context(format!(
+                "could not get a login url: {}",
+                command_output.message.unwrap_or_default(),
+            )));
+        }
+        Ok(command_output)
+    }
+
     pub fn auth_devhub(&mut self, path_to_auth_file: &str) -> Result<SfCliCommandOutput> {
         let output = if self.output.is_empty() {
             self.get_output(vec![
@@ -419,19 +921,119 @@ impl SalesforceCli {
         Ok(command_output)
     }
 
-    pub fn project_deploy(&mut self, path: &str) -> Result<SfCliCommandOutput> {
+    // without a test level sf picks the org's default
+    pub fn project_deploy(
+        &mut self,
+        path: &str,
+        test_level: Option<&str>,
+    ) -> Result<SfCliCommandOutput> {
         self.progress_bar
             .to_owned()
             .with_message(format!("Deploying metadata from {:?}", path))
             .enable_steady_tick(Duration::from_millis(120));
         let output = if self.output.is_empty() {
             let target_org = self.target_org.clone();
+            let mut args = vec!["project", "deploy", "start", "-d", path];
+            if let Some(test_level) = test_level {
+                args.extend(["-l", test_level]);
+            }
+            args.extend(["--json", "-o", target_org.as_str()]);
+            self.get_output(args)?
+        } else {
+            self.output.clone()
+        };
+
+        self.handle_deploy_output(output, "could not deploy metadata")
+    }
+
+    pub fn project_deploy_manifest(
+        &mut self,
+        manifest: &str,
+        pre_destructive_changes: Option<&str>,
+        post_destructive_changes: Option<&str>,
+        test_level: Option<&str>,
+    ) -> Result<SfCliCommandOutput> {
+        self.progress_bar
+            .to_owned()
+            .with_message(format!("Deploying metadata in {:?}", manifest))
+            .enable_steady_tick(Duration::from_millis(120));
+        let output = if self.output.is_empty() {
+            let target_org = self.target_org.clone();
+            let mut args = vec!["project", "deploy", "start", "-x", manifest];
+            if let Some(path) = pre_destructive_changes {
+                args.extend(["--pre-destructive-changes", path]);
+            }
+            if let Some(path) = post_destructive_changes {
+                args.extend(["--post-destructive-changes", path]);
+            }
+            if let Some(test_level) = test_level {
+                args.extend(["-l", test_level]);
+            }
+            let wait_minutes = self.wait_minutes.to_string();
+            args.extend([
+                "-w",
+                wait_minutes.as_str(),
+                "--json",
+                "-o",
+                target_org.as_str(),
+            ]);
+            self.get_output(args)?
+        } else {
+            self.output.clone()
+        };
+
+        self.handle_deploy_output(output, "could not deploy metadata")
+    }
+
+    pub fn project_deploy_validate(
+        &mut self,
+        paths: &[&str],
+        test_level: &str,
+    ) -> Result<SfCliCommandOutput> {
+        self.progress_bar
+            .to_owned()
+            .with_message(format!("Validating deployment of {:?}", paths))
+            .enable_steady_tick(Duration::from_millis(120));
+        let output = if self.output.is_empty() {
+            let target_org = self.target_org.clone();
+            let wait_minutes = self.wait_minutes.to_string();
+            let mut args = vec!["project", "deploy", "validate"];
+            for path in paths {
+                args.extend(["-d", path]);
+            }
+            args.extend([
+                "-l",
+                test_level,
+                "-w",
+                wait_minutes.as_str(),
+                "--json",
+                "-o",
+                target_org.as_str(),
+            ]);
+            self.get_output(args)?
+        } else {
+            self.output.clone()
+        };
+
+        self.handle_deploy_output(output, "could not validate deployment")
+    }
+
+    pub fn project_deploy_quick(&mut self, job_id: &str) -> Result<SfCliCommandOutput> {
+        self.progress_bar
+            .to_owned()
+            .with_message(format!("Quick deploying validated deployment {}", job_id))
+            .enable_steady_tick(Duration::from_millis(120));
+        let output = if self.output.is_empty() {
+            let target_org = self.target_org.clone();
+            let wait_minutes = self.wait_minutes.to_string();
             self.get_output(vec![
                 "project",
                 "deploy",
-                "start",
-                "-d",
-                path,
+                "quick",
+                "--job-id",
+                job_id,
+                "-w",
+                wait_minutes.as_str(),
                 "--json",
                 "-o",
                 target_org.as_str(),
@@ -440,31 +1042,53 @@ impl SalesforceCli {
             self.output.clone()
         };
 
+        self.handle_deploy_output(output, "could not quick deploy")
+    }
+
+    pub fn query(&mut self, query: &str, use_tooling_api: bool) -> Result<SfCliCommandOutput> {
+        let output = if self.output.is_empty() {
+            let target_org = self.target_org.clone();
+            let mut args = vec![
+                "data",
+                "query",
+                "-q",
+                query,
+                "--json",
+                "-o",
+                target_org.as_str(),
+            ];
+            if use_tooling_api {
+                args.push("--use-tooling-api");
+            }
+            self.get_output(args)?
+        } else {
+            self.output.clone()
+        };
+
         let command_output: SfCliCommandOutput = serde_json::from_str(output.as_str())
             .expect("could not deserialize sf cli command output");
         if command_output.status != 0 {
             return Err(anyhow!(SfCliError).context(format!(
-                "could not deploy metadata: {}",
-                command_output.message.unwrap(),
+                "could not run query: {}",
+                command_output.message.unwrap_or_default(),
             )));
         }
-        self.progress_bar.finish();
-        print_stdout(command_output.get_formatted_results())?;
         Ok(command_output)
     }
 
-    pub fn exec_anonymous(&mut self, path: &str) -> Result<SfCliCommandOutput> {
+    pub fn import_data_tree(&mut self, plan: &str) -> Result<SfCliCommandOutput> {
         self.progress_bar
             .to_owned()
-            .with_message(format!("Executing anonymous apex script at {:?}", path))
+            .with_message(format!("Importing data from plan {:?}", plan))
             .enable_steady_tick(Duration::from_millis(120));
         let output = if self.output.is_empty() {
             let target_org = self.target_org.clone();
             self.get_output(vec![
-                "apex",
-                "run",
-                "--file",
-                path,
+                "data",
+                "import",
+                "tree",
+                "--plan",
+                plan,
                 "--json",
                 "-o",
                 target_org.as_str(),
@@ -477,66 +1101,45 @@ impl SalesforceCli {
             .expect("could not deserialize sf cli command output");
         if command_output.status != 0 {
             return Err(anyhow!(SfCliError).context(format!(
-                "could not execute anonymous apex: {}",
-                command_output.message.unwrap(),
+                "could not import data: {}",
+                command_output.message.unwrap_or_default(),
             )));
         }
-
         self.progress_bar.finish();
         print_stdout(command_output.get_formatted_results())?;
         Ok(command_output)
     }
 
-    pub fn run_tests(&mut self) -> Result<SfCliCommandOutput> {
-        self.progress_bar
-            .to_owned()
-            .with_message("Running apex tests")
-            .enable_steady_tick(Duration::from_millis(120));
+    // the orgs authorized on this machine
+    pub fn list_orgs(&mut self) -> Result<SfCliCommandOutput> {
         let output = if self.output.is_empty() {
-            let target_org = self.target_org.clone();
-            self.get_output(vec![
-                "apex",
-                "run",
-                "test",
-                "-c",
-                "-l",
-                "RunLocalTests",
-                "-w",
-                "60",
-                "--json",
-                "--target-org",
-                target_org.as_str(),
-            ])?
+            self.get_output(vec!["org", "list", "--json"])?
         } else {
             self.output.clone()
         };
 
         let command_output: SfCliCommandOutput = serde_json::from_str(output.as_str())
             .expect("could not deserialize sf cli command output");
-
-        // I do not know why the status for this is 100
-        if command_output.status != 100 {
+        if command_output.status != 0 {
             return Err(anyhow!(SfCliError).context(format!(
-                "could not run apex tests: {}",
-                command_output.message.unwrap(),
+                "could not list orgs: {}",
+                command_output.message.unwrap_or_default(),
             )));
         }
-        self.progress_bar.finish();
-        print_stdout(command_output.get_formatted_results())?;
         Ok(command_output)
     }
 
-    // TODO: handle packages with keys
-    pub fn install_package(&mut self, package_id: &str) -> Result<SfCliCommandOutput> {
+    pub fn delete_record(&mut self, sobject: &str, id: &str) -> Result<SfCliCommandOutput> {
         let output = if self.output.is_empty() {
             let target_org = self.target_org.clone();
             self.get_output(vec![
-                "package",
-                "install",
-                "--package",
-                package_id,
-                "-w",
-                "60",
+                "data",
+                "delete",
+                "record",
+                "-s",
+                sobject,
+                "-i",
+                id,
                 "--json",
                 "-o",
                 target_org.as_str(),
@@ -549,33 +1152,373 @@ impl SalesforceCli {
             .expect("could not deserialize sf cli command output");
         if command_output.status != 0 {
             return Err(anyhow!(SfCliError).context(format!(
-                "could not install package: {}",
-                command_output.message.unwrap(),
+                "could not delete {} {}: {}",
+                sobject,
+                id,
+                command_output.message.unwrap_or_default(),
             )));
         }
         Ok(command_output)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use cli_table::print_stdout;
+    // scratch orgs are not sandboxes either, but unlike production they expire
+    pub fn is_production(&mut self) -> Result<bool> {
+        let command_output = self.query(
+            "SELECT IsSandbox, TrialExpirationDate FROM Organization",
+            false,
+        )?;
+        match command_output.result {
+            Some(CliResult::Query { records, .. }) if !records.is_empty() => {
+                Ok(records[0]["IsSandbox"] == Value::Bool(false)
+                    && records[0]["TrialExpirationDate"].is_null())
+            }
+            _ => Err(anyhow!(SfCliError).context("could not determine the type of the org")),
+        }
+    }
 
-    #[test]
-    fn it_should_create_a_scratch_org() {
-        let input = r#"{
-  "status": 0,
-  "result": {
-    "username": "test@example.com",
-    "scratchOrgInfo": {
-      "attributes": {
-        "type": "ScratchOrgInfo",
-        "url": "/services/data/v62.0/sobjects/ScratchOrgInfo/2SRbm000000H9ZxGAK"
-      },
-      "Id": "1",
-      "OwnerId": "1",
-      "IsDeleted": false,
+    fn handle_deploy_output(
+        &mut self,
+        output: String,
+        error_message: &str,
+    ) -> Result<SfCliCommandOutput> {
+        let command_output: SfCliCommandOutput = serde_json::from_str(output.as_str())
+            .expect("could not deserialize sf cli command output");
+        github::report(&command_output)?;
+        self.progress_bar.finish();
+        if let Some(CliResult::ProjectDeploy { details, files, .. }) = &command_output.result {
+            print_stdout(command_output.get_formatted_results())?;
+            if !details.component_failures.is_empty() {
+                print_stdout(details.get_failure_results(files))?;
+            }
+        }
+        if command_output.status != 0 {
+            return Err(anyhow!(SfCliError).context(format!(
+                "{}: {}",
+                error_message,
+                command_output.message.unwrap_or_default(),
+            )));
+        }
+        Ok(command_output)
+    }
+
+    pub fn exec_anonymous(
+        &mut self,
+        path: &str,
+        log_file: Option<&Path>,
+    ) -> Result<SfCliCommandOutput> {
+        self.progress_bar
+            .to_owned()
+            .with_message(format!("Executing anonymous apex script at {:?}", path))
+            .enable_steady_tick(Duration::from_millis(120));
+        let output = if self.output.is_empty() {
+            let target_org = self.target_org.clone();
+            self.get_output(vec![
+                "apex",
+                "run",
+                "--file",
+                path,
+                "--json",
+                "-o",
+                target_org.as_str(),
+            ])?
+        } else {
+            self.output.clone()
+        };
+
+        let command_output: SfCliCommandOutput = serde_json::from_str(output.as_str())
+            .expect("could not deserialize sf cli command output");
+        self.progress_bar.finish();
+        let Some(CliResult::ExecuteAnonymousApex {
+            success,
+            compile_problem,
+            exception_message,
+            exception_stack_trace,
+            compiled,
+            line,
+            column,
+            logs,
+        }) = &command_output.result
+        else {
+            return Err(anyhow!(SfCliError).context(format!(
+                "could not execute anonymous apex: {}",
+                command_output.message.unwrap_or_default(),
+            )));
+        };
+
+        if let (Some(log_file), Some(logs)) = (log_file, logs) {
+            fs::write(log_file, logs)?;
+        }
+        print_stdout(command_output.get_formatted_results())?;
+
+        if !compiled && !compile_problem.is_empty() {
+            return Err(anyhow!(SfCliError).context(format!(
+                "could not compile anonymous apex {} at line {}, column {}: {}",
+                path, line, column, compile_problem
+            )));
+        }
+        if !success || command_output.status != 0 {
+            return Err(anyhow!(SfCliError).context(format!(
+                "anonymous apex {} failed: {}\n{}",
+                path, exception_message, exception_stack_trace
+            )));
+        }
+        Ok(command_output)
+    }
+
+    pub fn run_tests(&mut self, test_level: &str) -> Result<SfCliCommandOutput> {
+        self.progress_bar
+            .to_owned()
+            .with_message("Running apex tests")
+            .enable_steady_tick(Duration::from_millis(120));
+        let output = if self.output.is_empty() {
+            let target_org = self.target_org.clone();
+            let wait_minutes = self.wait_minutes.to_string();
+            self.get_output(vec![
+                "apex",
+                "run",
+                "test",
+                "-c",
+                "-l",
+                test_level,
+                "-w",
+                wait_minutes.as_str(),
+                "--json",
+                "--target-org",
+                target_org.as_str(),
+            ])?
+        } else {
+            self.output.clone()
+        };
+
+        let command_output: SfCliCommandOutput = serde_json::from_str(output.as_str())
+            .expect("could not deserialize sf cli command output");
+        github::report(&command_output)?;
+
+        // sf exits with 100 when the run completed with failing tests
+        if command_output.status != 0 && command_output.status != 100 {
+            return Err(anyhow!(SfCliError).context(format!(
+                "could not run apex tests: {}",
+                command_output.message.unwrap(),
+            )));
+        }
+        self.progress_bar.finish();
+        print_stdout(command_output.get_formatted_results())?;
+        Ok(command_output)
+    }
+
+    // fields are name=value pairs for the User record, plus sf's profileName, permsets and generatePassword
+    pub fn create_user(&mut self, alias: &str, fields: &[String]) -> Result<SfCliCommandOutput> {
+        let output = if self.output.is_empty() {
+            let target_org = self.target_org.clone();
+            let mut args = vec![
+                "org",
+                "create",
+                "user",
+                "--set-alias",
+                alias,
+                "--json",
+                "-o",
+                target_org.as_str(),
+            ];
+            args.extend(fields.iter().map(|x| x.as_str()));
+            self.get_output(args)?
+        } else {
+            self.output.clone()
+        };
+
+        let command_output: SfCliCommandOutput = serde_json::from_str(output.as_str())
+            .expect("could not deserialize sf cli command output");
+        if command_output.status != 0 {
+            return Err(anyhow!(SfCliError).context(format!(
+                "could not create user {}: {}",
+                alias,
+                command_output.message.unwrap_or_default(),
+            )));
+        }
+        Ok(command_output)
+    }
+
+    pub fn display_user(&mut self, user: &str) -> Result<SfCliCommandOutput> {
+        let output = if self.output.is_empty() {
+            self.get_output(vec!["org", "display", "user", "--json", "-o", user])?
+        } else {
+            self.output.clone()
+        };
+
+        let command_output: SfCliCommandOutput = serde_json::from_str(output.as_str())
+            .expect("could not deserialize sf cli command output");
+        if command_output.status != 0 {
+            return Err(anyhow!(SfCliError).context(format!(
+                "could not display user {}: {}",
+                user,
+                command_output.message.unwrap_or_default(),
+            )));
+        }
+        Ok(command_output)
+    }
+
+    pub fn assign_permission_sets(&mut self, names: &[String]) -> Result<SfCliCommandOutput> {
+        self.assign_permissions("permset", names)
+    }
+
+    pub fn assign_permission_set_licenses(
+        &mut self,
+        names: &[String],
+    ) -> Result<SfCliCommandOutput> {
+        self.assign_permissions("permsetlicense", names)
+    }
+
+    // permissions that are already assigned are reported as duplicates and count as assigned
+    fn assign_permissions(
+        &mut self,
+        permission_type: &str,
+        names: &[String],
+    ) -> Result<SfCliCommandOutput> {
+        let output = if self.output.is_empty() {
+            let target_org = self.target_org.clone();
+            let mut args = vec!["org", "assign", permission_type];
+            for name in names {
+                args.push("-n");
+                args.push(name.as_str());
+            }
+            args.append(&mut vec!["--json", "-o", target_org.as_str()]);
+            self.get_output(args)?
+        } else {
+            self.output.clone()
+        };
+
+        let command_output: SfCliCommandOutput = serde_json::from_str(output.as_str())
+            .expect("could not deserialize sf cli command output");
+        let Some(CliResult::AssignPermissions { failures, .. }) = &command_output.result else {
+            return Err(anyhow!(SfCliError).context(format!(
+                "could not assign {}: {}",
+                permission_type,
+                command_output.message.unwrap_or_default(),
+            )));
+        };
+        let failures = failures
+            .iter()
+            .filter(|x| !x.message.to_lowercase().contains("duplicate"))
+            .map(|x| format!("{}: {}", x.name, x.message))
+            .collect::<Vec<_>>();
+        if !failures.is_empty() {
+            return Err(anyhow!(SfCliError).context(format!(
+                "could not assign {}: {}",
+                permission_type,
+                failures.join(", ")
+            )));
+        }
+        Ok(command_output)
+    }
+
+    // the packages owned by the target Dev Hub
+    pub fn list_packages(&mut self) -> Result<SfCliCommandOutput> {
+        let output = if self.output.is_empty() {
+            let target_org = self.target_org.clone();
+            self.get_output(vec!["package", "list", "-v", target_org.as_str(), "--json"])?
+        } else {
+            self.output.clone()
+        };
+
+        let command_output: SfCliCommandOutput = serde_json::from_str(output.as_str())
+            .expect("could not deserialize sf cli command output");
+        if command_output.status != 0 {
+            return Err(anyhow!(SfCliError).context(format!(
+                "could not list packages: {}",
+                command_output.message.unwrap_or_default(),
+            )));
+        }
+        Ok(command_output)
+    }
+
+    pub fn list_package_versions(
+        &mut self,
+        package_ids: &[String],
+        released: bool,
+    ) -> Result<SfCliCommandOutput> {
+        let output = if self.output.is_empty() {
+            let target_org = self.target_org.clone();
+            let packages = package_ids.join(",");
+            let mut args = vec![
+                "package",
+                "version",
+                "list",
+                "-v",
+                target_org.as_str(),
+                "--packages",
+                packages.as_str(),
+                "--json",
+            ];
+            if released {
+                args.push("--released");
+            }
+            self.get_output(args)?
+        } else {
+            self.output.clone()
+        };
+
+        let command_output: SfCliCommandOutput = serde_json::from_str(output.as_str())
+            .expect("could not deserialize sf cli command output");
+        if command_output.status != 0 {
+            return Err(anyhow!(SfCliError).context(format!(
+                "could not list package versions: {}",
+                command_output.message.unwrap_or_default(),
+            )));
+        }
+        Ok(command_output)
+    }
+
+    // TODO: handle packages with keys
+    pub fn install_package(&mut self, package_id: &str) -> Result<SfCliCommandOutput> {
+        let output = if self.output.is_empty() {
+            let target_org = self.target_org.clone();
+            let wait_minutes = self.wait_minutes.to_string();
+            self.get_output(vec![
+                "package",
+                "install",
+                "--package",
+                package_id,
+                "-w",
+                wait_minutes.as_str(),
+                "--json",
+                "-o",
+                target_org.as_str(),
+            ])?
+        } else {
+            self.output.clone()
+        };
+
+        let command_output: SfCliCommandOutput = serde_json::from_str(output.as_str())
+            .expect("could not deserialize sf cli command output");
+        if command_output.status != 0 {
+            return Err(anyhow!(SfCliError).context(format!(
+                "could not install package: {}",
+                command_output.message.unwrap(),
+            )));
+        }
+        Ok(command_output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cli_table::print_stdout;
+
+    #[test]
+    fn it_should_create_a_scratch_org() {
+        let input = r#"{
+  "status": 0,
+  "result": {
+    "username": "test@example.com",
+    "scratchOrgInfo": {
+      "attributes": {
+        "type": "ScratchOrgInfo",
+        "url": "/services/data/v62.0/sobjects/ScratchOrgInfo/2SRbm000000H9ZxGAK"
+      },
+      "Id": "1",
+      "OwnerId": "1",
+      "IsDeleted": false,
       "Name": "00000005",
       "CreatedDate": "2025-01-01T05:29:34.000+0000",
       "CreatedById": "1",
@@ -642,7 +1585,15 @@ mod tests {
 
         let mut cli = SalesforceCli::new(Some(String::from("test")));
         cli.mock_cli_output(String::from(input));
-        let command_output = &cli.create_scratch_org("devhub");
+        let command_output = &cli.create_scratch_org(
+            "devhub",
+            &ScratchOrgOptions {
+                definition_file: Some(String::from("config/project-scratch-def.json")),
+                duration_days: Some(1),
+                edition: None,
+                no_namespace: false,
+            },
+        );
         assert!(command_output.is_ok());
 
         let result = command_output.as_ref().unwrap().result.as_ref();
@@ -809,7 +1760,7 @@ mod tests {
 
         let mut cli = SalesforceCli::new(Some(String::from("test")));
         cli.mock_cli_output(String::from(input));
-        let command_output = &cli.project_deploy("path");
+        let command_output = &cli.project_deploy("path", None);
         assert!(command_output.is_ok());
 
         let result = command_output.as_ref().unwrap().result.as_ref();
@@ -821,6 +1772,7 @@ mod tests {
                 .unwrap()
                 .as_project_deploy()
                 .unwrap()
+                .0
                 .component_successes
                 .len()
         );
@@ -847,7 +1799,7 @@ mod tests {
 
         let mut cli = SalesforceCli::new(Some(String::from("test")));
         cli.mock_cli_output(String::from(input));
-        let command_output = &cli.exec_anonymous("path");
+        let command_output = &cli.exec_anonymous("path", None);
         assert!(command_output.is_ok());
 
         let result = command_output.as_ref().unwrap().result.as_ref();
@@ -861,6 +1813,57 @@ mod tests {
         assert!(print_stdout(command_output.as_ref().unwrap().get_formatted_results()).is_ok());
     }
 
+    #[test]
+    fn it_should_fail_on_anonymous_apex_problems() {
+        let compile_error = r#"{
+  "status": 0,
+  "result": {
+    "success": false,
+    "compiled": false,
+    "compileProblem": "Variable does not exist: foo",
+    "exceptionMessage": "",
+    "exceptionStackTrace": "",
+    "line": 2,
+    "column": 9,
+    "logs": ""
+  },
+  "warnings": []
+}
+"#;
+        let exception = r#"{
+  "status": 0,
+  "result": {
+    "success": false,
+    "compiled": true,
+    "compileProblem": "",
+    "exceptionMessage": "System.NullPointerException: Attempt to de-reference a null object",
+    "exceptionStackTrace": "AnonymousBlock: line 3, column 1",
+    "line": 3,
+    "column": 1,
+    "logs": "62.0 APEX_CODE,DEBUG\nEXCEPTION_THROWN"
+  },
+  "warnings": []
+}
+"#;
+
+        let mut cli = SalesforceCli::new(Some(String::from("test")));
+        cli.mock_cli_output(String::from(compile_error));
+        let error = cli.exec_anonymous("script.apex", None).unwrap_err();
+        assert!(format!("{:#}", error).contains("at line 2, column 9"));
+
+        let log_file = std::env::temp_dir().join(format!("ht-apex-{}.log", rand::random::<u64>()));
+        cli.mock_cli_output(String::from(exception));
+        let error = cli
+            .exec_anonymous("script.apex", Some(&log_file))
+            .unwrap_err();
+        assert!(format!("{:#}", error).contains("System.NullPointerException"));
+        assert_eq!(
+            "62.0 APEX_CODE,DEBUG\nEXCEPTION_THROWN",
+            fs::read_to_string(&log_file).unwrap()
+        );
+        fs::remove_file(log_file).unwrap();
+    }
+
     #[test]
     fn it_should_run_apex_tests() {
         let input = r#"{
@@ -948,7 +1951,7 @@ mod tests {
 
         let mut cli = SalesforceCli::new(Some(String::from("test")));
         cli.mock_cli_output(String::from(input));
-        let command_output = &cli.run_tests();
+        let command_output = &cli.run_tests("RunLocalTests");
         assert!(command_output.is_ok());
 
         let result = command_output.as_ref().unwrap().result.as_ref();
@@ -956,9 +1959,329 @@ mod tests {
         assert!(matches!(result.unwrap(), CliResult::RunApexTests { .. }));
         assert_eq!(1, result.unwrap().as_run_apex_tests().unwrap().0.failing);
         assert_eq!(2, result.unwrap().as_run_apex_tests().unwrap().1.len());
+        assert_eq!(Some(0.0), command_output.as_ref().unwrap().get_coverage());
         assert!(print_stdout(command_output.as_ref().unwrap().get_formatted_results()).is_ok());
     }
 
+    #[test]
+    fn it_should_accept_a_passing_test_run() {
+        let input = r#"{
+  "status": 0,
+  "result": {
+    "summary": {
+      "failRate": "0%",
+      "failing": 0,
+      "outcome": "Passed",
+      "passing": 1,
+      "testsRan": 1,
+      "testExecutionTime": "8 ms",
+      "orgWideCoverage": "100%",
+      "testRunCoverage": "100%"
+    },
+    "tests": [
+      {
+        "StackTrace": null,
+        "Message": null,
+        "MethodName": "runTest",
+        "Outcome": "Pass",
+        "RunTime": 8,
+        "FullName": "TestClass.runTest"
+      }
+    ]
+  },
+  "warnings": []
+}
+"#;
+
+        let mut cli = SalesforceCli::new(Some(String::from("test")));
+        cli.mock_cli_output(String::from(input));
+        let command_output = cli.run_tests("RunLocalTests").unwrap();
+        assert_eq!(Some(100.0), command_output.get_coverage());
+    }
+
+    #[test]
+    fn it_should_locate_failing_tests_in_the_source_files() {
+        let source_files = BTreeMap::from([(
+            String::from("TestClass.cls"),
+            String::from("force-app/main/default/classes/TestClass.cls"),
+        )]);
+        assert_eq!(
+            Some((
+                String::from("force-app/main/default/classes/TestClass.cls"),
+                10,
+                1
+            )),
+            get_stack_trace_location(
+                "Class.TestClass.runTest2: line 10, column 1\nClass.Other.run: line 2, column 1",
+                &source_files
+            )
+        );
+        assert_eq!(
+            None,
+            get_stack_trace_location("Class.Missing.run: line 1, column 1", &source_files)
+        );
+    }
+
+    #[test]
+    fn it_should_annotate_deploy_failures_with_source_paths() {
+        let input = r#"{
+  "status": 1,
+  "name": "FailedDeployError",
+  "message": "Deploy failed.",
+  "result": {
+    "details": {
+      "componentSuccesses": [],
+      "componentFailures": [
+        {
+          "changed": false,
+          "columnNumber": 5,
+          "componentType": "ApexClass",
+          "created": false,
+          "deleted": false,
+          "fileName": "classes/Test.cls",
+          "fullName": "Test",
+          "lineNumber": 3,
+          "problem": "Variable does not exist: foo",
+          "problemType": "Error",
+          "success": false
+        }
+      ]
+    },
+    "files": [
+      {
+        "fullName": "Test",
+        "type": "ApexClass",
+        "state": "Failed",
+        "filePath": "force-app/main/default/classes/Test.cls"
+      },
+      {
+        "fullName": "Test",
+        "type": "ApexClass",
+        "state": "Failed",
+        "filePath": "force-app/main/default/classes/Test.cls-meta.xml"
+      }
+    ]
+  }
+}
+"#;
+
+        let command_output: SfCliCommandOutput = serde_json::from_str(input).unwrap();
+        let annotations = command_output.get_annotations();
+        assert_eq!(1, annotations.len());
+        assert_eq!(
+            Some(String::from("force-app/main/default/classes/Test.cls")),
+            annotations[0].file
+        );
+        assert_eq!(Some(3), annotations[0].line);
+        assert_eq!(Some(5), annotations[0].column);
+        assert_eq!("Variable does not exist: foo", annotations[0].message);
+        assert!(command_output
+            .get_markdown_summary()
+            .contains("| ApexClass | Test | force-app/main/default/classes/Test.cls |"));
+    }
+
+    #[test]
+    fn it_should_report_a_failed_deployment() {
+        let input = r#"{
+  "status": 1,
+  "name": "FailedDeployError",
+  "message": "Deploy failed.",
+  "result": {
+    "details": {
+      "componentSuccesses": {
+        "changed": true,
+        "componentType": "ApexClass",
+        "created": true,
+        "deleted": false,
+        "fileName": "classes/Other.cls",
+        "fullName": "Other",
+        "success": true
+      },
+      "componentFailures": {
+        "changed": false,
+        "columnNumber": 5,
+        "componentType": "ApexClass",
+        "created": false,
+        "deleted": false,
+        "fileName": "classes/Test.cls",
+        "fullName": "Test",
+        "lineNumber": 3,
+        "problem": "Variable does not exist: foo",
+        "problemType": "Error",
+        "success": false
+      }
+    },
+    "files": []
+  }
+}
+"#;
+
+        let mut cli = SalesforceCli::new(Some(String::from("test")));
+        cli.mock_cli_output(String::from(input));
+        let command_output = &cli.project_deploy("path", None);
+        assert!(command_output.is_err());
+
+        let command_output: SfCliCommandOutput = serde_json::from_str(input).unwrap();
+        let details = command_output
+            .result
+            .as_ref()
+            .unwrap()
+            .as_project_deploy()
+            .unwrap()
+            .0;
+        assert_eq!(1, details.component_failures.len());
+        assert_eq!(
+            Some(String::from("Error")),
+            details.component_failures[0].problem_type
+        );
+        assert_eq!(
+            Some(&1),
+            details.get_success_count_by_type().get("ApexClass")
+        );
+        assert!(print_stdout(command_output.get_formatted_results()).is_ok());
+        assert!(print_stdout(details.get_failure_results(&[])).is_ok());
+    }
+
+    #[test]
+    fn it_should_validate_a_deployment() {
+        let input = r#"{
+  "status": 0,
+  "result": {
+    "checkOnly": true,
+    "details": {
+      "componentSuccesses": [
+        {
+          "changed": true,
+          "componentType": "ApexClass",
+          "created": true,
+          "deleted": false,
+          "fileName": "classes/Test.cls",
+          "fullName": "Test",
+          "success": true
+        }
+      ],
+      "componentFailures": [],
+      "runTestResult": {
+        "codeCoverage": [
+          { "name": "Test", "numLocations": 10, "numLocationsNotCovered": 2 },
+          { "name": "Other", "numLocations": 10, "numLocationsNotCovered": 3 }
+        ]
+      }
+    },
+    "done": true,
+    "id": "0AfRt00000PqprFKAR",
+    "status": "Succeeded",
+    "success": true,
+    "files": []
+  },
+  "warnings": []
+}
+"#;
+
+        let mut cli = SalesforceCli::new(Some(String::from("test")));
+        cli.mock_cli_output(String::from(input));
+        let command_output = &cli.project_deploy_validate(&["force-app"], "RunLocalTests");
+        assert!(command_output.is_ok());
+
+        let result = command_output.as_ref().unwrap().result.as_ref();
+        assert_eq!(
+            "0AfRt00000PqprFKAR",
+            result.unwrap().as_project_deploy().unwrap().2
+        );
+        assert_eq!(Some(75.0), command_output.as_ref().unwrap().get_coverage());
+    }
+
+    #[test]
+    fn it_should_detect_a_production_org() {
+        let input = r#"{
+  "status": 0,
+  "result": {
+    "records": [
+      {
+        "attributes": {
+          "type": "Organization",
+          "url": "/services/data/v62.0/sobjects/Organization/00D000000000001"
+        },
+        "IsSandbox": false,
+        "TrialExpirationDate": null
+      }
+    ],
+    "totalSize": 1,
+    "done": true
+  },
+  "warnings": []
+}
+"#;
+
+        let mut cli = SalesforceCli::new(Some(String::from("test")));
+        cli.mock_cli_output(String::from(input));
+        assert!(cli.is_production().unwrap());
+
+        let scratch_org = input.replace("null", r#""2025-01-08T00:00:00.000+0000""#);
+        cli.mock_cli_output(scratch_org);
+        assert!(!cli.is_production().unwrap());
+    }
+
+    #[test]
+    fn it_should_import_a_data_plan() {
+        let input = r#"{
+  "status": 0,
+  "result": [
+    { "refId": "AccountRef1", "type": "Account", "id": "001000000000001" },
+    { "refId": "AccountRef2", "type": "Account", "id": "001000000000002" },
+    { "refId": "ContactRef1", "type": "Contact", "id": "003000000000001" }
+  ],
+  "warnings": []
+}
+"#;
+
+        let mut cli = SalesforceCli::new(Some(String::from("test")));
+        cli.mock_cli_output(String::from(input));
+        let command_output = &cli.import_data_tree("data/Account-Contact-plan.json");
+        assert!(command_output.is_ok());
+
+        let result = command_output.as_ref().unwrap().result.as_ref();
+        assert_eq!(3, result.unwrap().as_data_import().unwrap().len());
+        assert!(print_stdout(command_output.as_ref().unwrap().get_formatted_results()).is_ok());
+    }
+
+    #[test]
+    fn it_should_create_a_package_version() {
+        let input = r#"{
+  "status": 0,
+  "result": {
+    "Id": "08c000000000001",
+    "Status": "Success",
+    "Package2Id": "0Ho000000000001",
+    "Package2VersionId": "05i000000000001",
+    "SubscriberPackageVersionId": "04t000000000001",
+    "Tag": null,
+    "Branch": null,
+    "Error": [],
+    "CreatedDate": "2025-01-05 22:35",
+    "HasMetadataRemoved": false,
+    "CreatedBy": "005000000000001"
+  },
+  "warnings": []
+}
+"#;
+
+        let mut cli = SalesforceCli::new(None);
+        cli.mock_cli_output(String::from(input));
+        let command_output = &cli.create_package_version("devhub");
+        assert!(command_output.is_ok());
+
+        let result = command_output.as_ref().unwrap().result.as_ref();
+        assert!(matches!(
+            result.unwrap(),
+            CliResult::CreatePackageVersion { .. }
+        ));
+        assert_eq!(
+            &Some(String::from("04t000000000001")),
+            result.unwrap().as_create_package_version().unwrap().3
+        );
+    }
+
     #[test]
     fn it_should_install_a_package() {
         let input = r#"{
@@ -989,4 +2312,222 @@ mod tests {
         assert!(matches!(result.unwrap(), CliResult::PackageInstall { .. }));
         assert_eq!("SUCCESS", result.unwrap().as_package_install().unwrap());
     }
+
+    #[test]
+    fn it_should_get_a_login_url() {
+        let input = r#"{
+  "status": 0,
+  "result": {
+    "orgId": "00D000000000001",
+    "url": "https://test.my.salesforce.com/secur/frontdoor.jsp?sid=token",
+    "username": "test@example.com"
+  },
+  "warnings": []
+}
+"#;
+
+        let mut cli = SalesforceCli::new(Some(String::from("test")));
+        cli.mock_cli_output(String::from(input));
+        let command_output = cli.open_org().unwrap();
+        let (url, username, _) = command_output
+            .result
+            .as_ref()
+            .unwrap()
+            .as_open_org()
+            .unwrap();
+        assert_eq!(
+            "https://test.my.salesforce.com/secur/frontdoor.jsp?sid=token",
+            url
+        );
+        assert_eq!("test@example.com", username);
+    }
+
+    #[test]
+    fn it_should_list_the_local_orgs() {
+        let input = r#"{
+  "status": 0,
+  "result": {
+    "other": [],
+    "sandboxes": [],
+    "nonScratchOrgs": [
+      { "alias": "DevHub", "username": "hub@example.com", "isDevHub": true }
+    ],
+    "devHubs": [],
+    "scratchOrgs": [
+      { "alias": "project123", "username": "test-abc@example.com", "expirationDate": "2025-01-12" },
+      { "username": "test-def@example.com", "expirationDate": "2025-01-12" }
+    ]
+  },
+  "warnings": []
+}
+"#;
+
+        let mut cli = SalesforceCli::new(None);
+        cli.mock_cli_output(String::from(input));
+        let command_output = cli.list_orgs().unwrap();
+        let (scratch_orgs, _) = command_output
+            .result
+            .as_ref()
+            .unwrap()
+            .as_org_list()
+            .unwrap();
+        assert_eq!(2, scratch_orgs.len());
+        assert_eq!(Some(String::from("project123")), scratch_orgs[0].alias);
+        assert_eq!(None, scratch_orgs[1].alias);
+    }
+
+    #[test]
+    fn it_should_list_package_versions() {
+        let input = r#"{
+  "status": 0,
+  "result": [
+    {
+      "Package2Id": "0HoB00000004CFuKAM",
+      "Branch": null,
+      "MajorVersion": 3,
+      "MinorVersion": 1,
+      "PatchVersion": 0,
+      "BuildNumber": 2,
+      "Id": "05iB0000000000AAAA",
+      "SubscriberPackageVersionId": "04tB0000000000AAAA",
+      "Name": "v 3.1",
+      "Package2Name": "Expense Manager",
+      "Version": "3.1.0.2",
+      "IsPasswordProtected": false,
+      "IsReleased": true,
+      "ValidationSkipped": false,
+      "Alias": ""
+    }
+  ],
+  "warnings": []
+}
+"#;
+
+        let mut cli = SalesforceCli::new(Some(String::from("DevHub")));
+        cli.mock_cli_output(String::from(input));
+        let command_output = cli
+            .list_package_versions(&[String::from("0HoB00000004CFuKAM")], true)
+            .unwrap();
+        let versions = command_output
+            .result
+            .as_ref()
+            .unwrap()
+            .as_package_version_list()
+            .unwrap();
+        assert_eq!(1, versions.len());
+        assert_eq!("3.1.0.2", versions[0].version);
+        assert_eq!(
+            "04tB0000000000AAAA",
+            versions[0].subscriber_package_version_id
+        );
+
+        cli.mock_cli_output(String::from(
+            r#"{ "status": 0, "result": [{ "Id": "0HoB00000004CFuKAM", "Name": "Expense Manager", "ContainerOptions": "Unlocked" }] }"#,
+        ));
+        let command_output = cli.list_packages().unwrap();
+        let packages = command_output
+            .result
+            .as_ref()
+            .unwrap()
+            .as_package_list()
+            .unwrap();
+        assert_eq!("Expense Manager", packages[0].name);
+    }
+
+    #[test]
+    fn it_should_create_a_user() {
+        let input = r#"{
+  "status": 0,
+  "result": {
+    "orgId": "00D000000000001",
+    "permissionSetAssignments": ["Expense_Approver"],
+    "fields": {
+      "alias": "approver",
+      "email": "test@example.com",
+      "localesidkey": "fr_FR",
+      "profileid": "00e000000000001",
+      "username": "test-abc@example.com",
+      "id": "005000000000002"
+    }
+  },
+  "warnings": []
+}
+"#;
+
+        let mut cli = SalesforceCli::new(Some(String::from("test")));
+        cli.mock_cli_output(String::from(input));
+        let command_output = cli
+            .create_user("approver", &[String::from("profileName=Standard User")])
+            .unwrap();
+        let (_, fields) = command_output
+            .result
+            .as_ref()
+            .unwrap()
+            .as_create_user()
+            .unwrap();
+        assert_eq!("test-abc@example.com", fields["username"]);
+    }
+
+    #[test]
+    fn it_should_display_a_user() {
+        let input = r#"{
+  "status": 0,
+  "result": {
+    "username": "test-abc@example.com",
+    "profileName": "Standard User",
+    "id": "005000000000002",
+    "orgId": "00D000000000001",
+    "accessToken": "00D000000000001!token",
+    "instanceUrl": "https://test.my.salesforce.com",
+    "loginUrl": "https://test.my.salesforce.com",
+    "alias": "approver",
+    "password": "secret"
+  },
+  "warnings": []
+}
+"#;
+
+        let mut cli = SalesforceCli::new(Some(String::from("test")));
+        cli.mock_cli_output(String::from(input));
+        let command_output = cli.display_user("approver").unwrap();
+        let (username, _, _, _, password) = command_output
+            .result
+            .as_ref()
+            .unwrap()
+            .as_display_user()
+            .unwrap();
+        assert_eq!("test-abc@example.com", username);
+        assert_eq!(&Some(String::from("secret")), password);
+    }
+
+    #[test]
+    fn it_should_treat_assigned_permissions_as_assigned() {
+        let input = r#"{
+  "status": 1,
+  "result": {
+    "successes": [{ "name": "test@example.com", "value": "Expense_Admin" }],
+    "failures": [
+      {
+        "name": "test@example.com",
+        "message": "Duplicate PermissionSetAssignment: Assignee=005000000000001, PermissionSet=0PS000000000001"
+      }
+    ]
+  },
+  "warnings": []
+}
+"#;
+
+        let mut cli = SalesforceCli::new(Some(String::from("test")));
+        cli.mock_cli_output(String::from(input));
+        let names = vec![String::from("Expense_Admin"), String::from("Expense_User")];
+        assert!(cli.assign_permission_sets(&names).is_ok());
+
+        let input = input.replace(
+            "Duplicate PermissionSetAssignment",
+            "Permission set not found",
+        );
+        cli.mock_cli_output(input);
+        let error = cli.assign_permission_sets(&names).unwrap_err();
+        assert!(format!("{:#}", error).contains("Permission set not found"));
+    }
 }
@@ -0,0 +1,290 @@
+use crate::cli::sf::{CliResult, PackageInfo, PackageVersionInfo, SalesforceCli};
+use crate::project_config::{self, SalesforceProjectConfig};
+use anyhow::{anyhow, Result};
+use cli_table::{print_stdout, Cell, Style, Table};
+use indexmap::IndexMap;
+use std::{
+    collections::BTreeSet,
+    fmt::{self, Display, Formatter},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Change {
+    Added,
+    Updated,
+    Removed,
+}
+
+#[derive(Debug, PartialEq)]
+struct AliasChange {
+    alias: String,
+    id: String,
+    change: Change,
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added => write!(f, "added"),
+            Change::Updated => write!(f, "updated"),
+            Change::Removed => write!(f, "removed"),
+        }
+    }
+}
+
+pub fn list(project_config: &SalesforceProjectConfig) -> Result<()> {
+    let aliases = project_config.get_package_aliases();
+    if aliases.is_empty() {
+        println!("sfdx-project.json has no package aliases");
+        return Ok(());
+    }
+    print_stdout(
+        aliases
+            .iter()
+            .map(|(alias, id)| {
+                vec![
+                    alias.cell(),
+                    id.cell(),
+                    project_config.is_alias_used(alias).cell(),
+                ]
+            })
+            .collect::<Vec<_>>()
+            .table()
+            .title(vec![
+                "Alias".cell().bold(true),
+                "Id".cell().bold(true),
+                "Used".cell().bold(true),
+            ])
+            .bold(true),
+    )?;
+    Ok(())
+}
+
+pub fn add(alias: &str, id: &str, project_config: &mut SalesforceProjectConfig) -> Result<()> {
+    if !id.starts_with("0Ho") && !id.starts_with("04t") {
+        return Err(anyhow!(
+            "{} is not a package (0Ho) or package version (04t) id",
+            id
+        ));
+    }
+    match project_config.set_package_alias(alias, id) {
+        Some(previous) => println!("Changed {} from {} to {}", alias, previous, id),
+        None => println!("Added {} for {}", alias, id),
+    }
+    project_config.write()
+}
+
+pub fn remove(alias: &str, project_config: &mut SalesforceProjectConfig) -> Result<()> {
+    if project_config.get_required_aliases().contains(alias) {
+        return Err(anyhow!(
+            "{} is used by a package or dependency in sfdx-project.json",
+            alias
+        ));
+    }
+    match project_config.remove_package_alias(alias) {
+        Some(id) => println!("Removed {} for {}", alias, id),
+        None => return Err(anyhow!("there is no package alias {}", alias)),
+    }
+    project_config.write()
+}
+
+// refreshes the version aliases of the project's packages from the Dev Hub and removes the
+// aliases nothing in the project uses
+pub fn sync(
+    devhub: &str,
+    dry_run: bool,
+    project_config: &mut SalesforceProjectConfig,
+) -> Result<()> {
+    let mut cli = SalesforceCli::new(Some(devhub.to_owned()));
+    let packages = match cli.list_packages()?.result {
+        Some(CliResult::PackageList(x)) => x,
+        _ => Vec::new(),
+    };
+    let aliases = project_config.get_package_aliases();
+    let referenced = project_config.get_referenced_packages();
+    let packages = get_referenced_packages(&aliases, &referenced, packages);
+
+    let versions = if packages.is_empty() {
+        Vec::new()
+    } else {
+        let ids = packages
+            .iter()
+            .map(|(_, x)| x.id.clone())
+            .collect::<Vec<_>>();
+        match cli.list_package_versions(&ids, true)?.result {
+            Some(CliResult::PackageVersionList(x)) => x,
+            _ => Vec::new(),
+        }
+    };
+
+    let changes = get_alias_changes(&aliases, &referenced, &packages, &versions);
+    if changes.is_empty() {
+        println!("packageAliases is up to date");
+        return Ok(());
+    }
+    print_stdout(
+        changes
+            .iter()
+            .map(|x| vec![x.alias.clone().cell(), x.id.clone().cell(), x.change.cell()])
+            .collect::<Vec<_>>()
+            .table()
+            .title(vec![
+                "Alias".cell().bold(true),
+                "Id".cell().bold(true),
+                "Change".cell().bold(true),
+            ])
+            .bold(true),
+    )?;
+    if dry_run {
+        return Ok(());
+    }
+
+    for change in &changes {
+        match change.change {
+            Change::Added | Change::Updated => {
+                project_config.set_package_alias(&change.alias, &change.id);
+            }
+            Change::Removed => {
+                project_config.remove_package_alias(&change.alias);
+            }
+        }
+    }
+    project_config.write()
+}
+
+// the Dev Hub packages the project uses, by the name the project knows them as
+fn get_referenced_packages(
+    aliases: &IndexMap<String, String>,
+    referenced: &BTreeSet<String>,
+    packages: Vec<PackageInfo>,
+) -> Vec<(String, PackageInfo)> {
+    referenced
+        .iter()
+        .filter_map(|name| {
+            packages
+                .iter()
+                .find(|x| aliases.get(name) == Some(&x.id))
+                .or_else(|| packages.iter().find(|x| &x.name == name))
+                .map(|x| (name.clone(), x.clone()))
+        })
+        .collect()
+}
+
+fn get_alias_changes(
+    aliases: &IndexMap<String, String>,
+    referenced: &BTreeSet<String>,
+    packages: &[(String, PackageInfo)],
+    versions: &[PackageVersionInfo],
+) -> Vec<AliasChange> {
+    let mut changes = Vec::new();
+    let mut set = |alias: String, id: &str| match aliases.get(&alias) {
+        None => changes.push(AliasChange {
+            alias,
+            id: id.to_owned(),
+            change: Change::Added,
+        }),
+        Some(x) if x != id => changes.push(AliasChange {
+            alias,
+            id: id.to_owned(),
+            change: Change::Updated,
+        }),
+        _ => {}
+    };
+
+    for (name, package) in packages {
+        // an alias pinned to a package version id is left alone
+        if !aliases.contains_key(name) {
+            set(name.clone(), &package.id);
+        }
+        for version in versions.iter().filter(|x| x.package2_id == package.id) {
+            if let Some(alias) = get_version_alias(name, &version.version) {
+                set(alias, &version.subscriber_package_version_id);
+            }
+        }
+    }
+
+    for (alias, id) in aliases {
+        if !referenced.contains(project_config::get_alias_package(alias)) {
+            changes.push(AliasChange {
+                alias: alias.clone(),
+                id: id.clone(),
+                change: Change::Removed,
+            });
+        }
+    }
+    changes
+}
+
+// sf names package versions like Name@1.2.0-3
+fn get_version_alias(name: &str, version: &str) -> Option<String> {
+    let (number, build) = version.rsplit_once('.')?;
+    Some(format!("{}@{}-{}", name, number, build))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_refresh_and_prune_aliases() {
+        let aliases = IndexMap::from([
+            (
+                String::from("Expense Manager"),
+                String::from("0HoB00000004CFuKAM"),
+            ),
+            (
+                String::from("Expense Manager@3.1.0-1"),
+                String::from("04tB00000000000OLD"),
+            ),
+            (String::from("A"), String::from("04tB00000000000000")),
+            (String::from("Unused"), String::from("0HoB00000000000000")),
+        ]);
+        let referenced = BTreeSet::from([
+            String::from("A"),
+            String::from("Expense Manager"),
+            String::from("Test - Package"),
+        ]);
+        let packages = get_referenced_packages(
+            &aliases,
+            &referenced,
+            vec![
+                PackageInfo {
+                    id: String::from("0HoB00000004CFuKAM"),
+                    name: String::from("Expenses"),
+                },
+                PackageInfo {
+                    id: String::from("0HoB0000000000TEST"),
+                    name: String::from("Test - Package"),
+                },
+            ],
+        );
+        let version = |package2_id: &str, version: &str, id: &str| PackageVersionInfo {
+            package2_id: package2_id.to_owned(),
+            package2_name: String::new(),
+            version: version.to_owned(),
+            subscriber_package_version_id: id.to_owned(),
+            is_released: true,
+            validation_skipped: false,
+        };
+        let versions = vec![
+            version("0HoB00000004CFuKAM", "3.1.0.1", "04tB0000000000NEW1"),
+            version("0HoB00000004CFuKAM", "3.2.0.4", "04tB0000000000NEW2"),
+            version("0HoB0000000000TEST", "1.0.0.1", "04tB0000000000TST1"),
+        ];
+
+        let changes = get_alias_changes(&aliases, &referenced, &packages, &versions)
+            .into_iter()
+            .map(|x| (x.alias, x.change))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (String::from("Expense Manager@3.1.0-1"), Change::Updated),
+                (String::from("Expense Manager@3.2.0-4"), Change::Added),
+                (String::from("Test - Package"), Change::Added),
+                (String::from("Test - Package@1.0.0-1"), Change::Added),
+                (String::from("Unused"), Change::Removed),
+            ],
+            changes
+        );
+    }
+}
@@ -0,0 +1,24 @@
+use crate::config::Settings;
+use anyhow::Result;
+use cli_table::{print_stdout, Cell, Style, Table};
+
+pub fn show(settings: &Settings) -> Result<()> {
+    if let Some(environment) = &settings.environment {
+        println!("Environment {}", environment);
+    }
+    print_stdout(
+        settings
+            .list()
+            .into_iter()
+            .map(|(name, value, source)| vec![name.cell(), value.cell(), source.cell()])
+            .collect::<Vec<_>>()
+            .table()
+            .title(vec![
+                "Setting".cell().bold(true),
+                "Value".cell().bold(true),
+                "Source".cell().bold(true),
+            ])
+            .bold(true),
+    )?;
+    Ok(())
+}
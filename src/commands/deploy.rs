@@ -0,0 +1,214 @@
+use crate::approval;
+use crate::cli::sf::{SalesforceCli, SfCliCommandOutput};
+use crate::config::{Settings, Source};
+use crate::delta;
+use crate::destructive::{DestructiveChanges, Phase};
+use crate::metadata::Manifest;
+use crate::project::{HookCommand, HookContext, Hooks, Phase as HookPhase};
+use crate::project_config::{Package, SalesforceProjectConfig};
+use crate::state::{self, State};
+use anyhow::{anyhow, Result};
+use git2::Repository;
+use std::{env, path::Path};
+
+const DEFAULT_API_VERSION: &str = "62.0";
+
+pub struct DeltaOptions {
+    pub since: String,
+    pub until: String,
+    pub destructive_phase: Phase,
+    // Some(false) refuses to delete anything, None asks for production orgs
+    pub allow_destructive: Option<bool>,
+}
+
+pub fn run(
+    target_org: &str,
+    quick: &Option<Option<String>>,
+    delta: &Option<DeltaOptions>,
+    settings: &Settings,
+    approved: bool,
+    project_config: &SalesforceProjectConfig,
+) -> Result<()> {
+    approval::check(settings, "deploy to", target_org, approved)?;
+    let mut cli = SalesforceCli::new(Some(target_org.to_owned()));
+    cli.set_wait_minutes(settings.wait_minutes.value);
+    let hooks = Hooks::load(
+        HookCommand::Deploy,
+        HookContext {
+            target_org: target_org.to_owned(),
+            devhub: settings.devhub.value.clone(),
+        },
+        settings,
+        project_config.get_packages(),
+    )?;
+    let packages = project_config.get_packages();
+    // sf picks the test level for the org unless one is configured, a quick deploy ran its tests
+    // when it was validated
+    let test_level = (settings.test_level.source != Source::Default)
+        .then_some(settings.test_level.value.as_str());
+
+    match quick {
+        Some(job_id) => {
+            let mut state = State::read()?;
+            let job_id = match job_id {
+                Some(x) => x.to_owned(),
+                None => match state.get_validation(target_org) {
+                    Some(validation) => validation.job_id.clone(),
+                    None => {
+                        return Err(anyhow!(
+                            "no validated deployment found for {}, run `ht validate` first",
+                            target_org
+                        ))
+                    }
+                },
+            };
+            // ht validate validates every package, what another job id deploys is unknown
+            let is_validated_by_ht = state
+                .get_validation(target_org)
+                .is_some_and(|x| x.job_id == job_id);
+            let deployed = if is_validated_by_ht {
+                packages.iter().collect()
+            } else {
+                Vec::new()
+            };
+            hooks.run_all(HookPhase::Pre, &deployed, &mut cli)?;
+            cli.project_deploy_quick(&job_id)?;
+            hooks.run_all(HookPhase::Post, &deployed, &mut cli)?;
+
+            if is_validated_by_ht {
+                state.remove_validation(target_org);
+                state.write()?;
+            }
+        }
+        None => match delta {
+            Some(options) => {
+                let repo = Repository::discover(".")?;
+                let deployed = get_changed_packages(&repo, options, project_config)?;
+                hooks.run_all(HookPhase::Pre, &deployed, &mut cli)?;
+                if let Some(command_output) = deploy_delta(
+                    &mut cli,
+                    &repo,
+                    target_org,
+                    options,
+                    test_level,
+                    project_config,
+                )? {
+                    settings.check_coverage(command_output.get_coverage())?;
+                }
+                hooks.run_all(HookPhase::Post, &deployed, &mut cli)?;
+            }
+            None => {
+                hooks.run(HookPhase::Pre, None, &mut cli)?;
+                for package in packages {
+                    hooks.run(HookPhase::Pre, Some(package), &mut cli)?;
+                    let command_output = cli.project_deploy(package.path.as_str(), test_level)?;
+                    if test_level.is_some() {
+                        settings.check_coverage(command_output.get_coverage())?;
+                    }
+                    hooks.run(HookPhase::Post, Some(package), &mut cli)?;
+                }
+                hooks.run(HookPhase::Post, None, &mut cli)?;
+            }
+        },
+    }
+
+    Ok(())
+}
+
+// the packages with components that changed or were removed between the two refs
+fn get_changed_packages<'a>(
+    repo: &Repository,
+    options: &DeltaOptions,
+    project_config: &'a SalesforceProjectConfig,
+) -> Result<Vec<&'a Package>> {
+    let package_paths = get_repository_paths(repo, project_config)?;
+    let mut changed = Vec::new();
+    for (package, path) in project_config.get_packages().iter().zip(package_paths) {
+        if !delta::between(repo, &options.since, &options.until, &[path.as_str()])?.is_empty() {
+            changed.push(package);
+        }
+    }
+    Ok(changed)
+}
+
+fn deploy_delta(
+    cli: &mut SalesforceCli,
+    repo: &Repository,
+    target_org: &str,
+    options: &DeltaOptions,
+    test_level: Option<&str>,
+    project_config: &SalesforceProjectConfig,
+) -> Result<Option<SfCliCommandOutput>> {
+    let package_paths = get_repository_paths(repo, project_config)?;
+    let package_paths = package_paths.iter().map(|x| x.as_str()).collect::<Vec<_>>();
+    let delta = delta::between(repo, &options.since, &options.until, &package_paths)?;
+    if delta.is_empty() {
+        println!("No metadata changed since {}", options.since);
+        return Ok(None);
+    }
+    let destructive_changes = DestructiveChanges::new(delta.deleted, options.destructive_phase);
+    if options.allow_destructive == Some(false) && !destructive_changes.is_empty() {
+        return Err(anyhow!(
+            "{} components were removed but destructive changes are not allowed for {}",
+            destructive_changes.components.len(),
+            target_org
+        ));
+    }
+    destructive_changes.check_allowed(cli, target_org, options.allow_destructive == Some(true))?;
+
+    let api_version = project_config
+        .get_source_api_version()
+        .map(|x| x.as_str())
+        .unwrap_or(DEFAULT_API_VERSION);
+    let dir = state::get_dir("delta")?;
+
+    let mut package = Manifest::new(api_version);
+    delta.changed.iter().for_each(|x| package.add(x));
+    let package_path = dir.join("package.xml");
+    package.write(&package_path)?;
+
+    let destructive_changes_path = if destructive_changes.is_empty() {
+        None
+    } else {
+        Some(destructive_changes.write(&dir, api_version)?)
+    };
+    let destructive_changes_path = destructive_changes_path.as_ref().and_then(|x| x.to_str());
+
+    println!(
+        "Deploying {} changed and deleting {} removed components since {}",
+        delta.changed.len(),
+        destructive_changes.components.len(),
+        options.since
+    );
+    let (pre, post) = match destructive_changes.phase {
+        Phase::Pre => (destructive_changes_path, None),
+        Phase::Post => (None, destructive_changes_path),
+    };
+    let command_output = cli.project_deploy_manifest(
+        package_path
+            .to_str()
+            .ok_or(anyhow!("invalid manifest path"))?,
+        pre,
+        post,
+        test_level,
+    )?;
+    Ok(test_level.is_some().then_some(command_output))
+}
+
+// package paths are relative to the project, which can be a subdirectory of the repository
+fn get_repository_paths(
+    repo: &Repository,
+    project_config: &SalesforceProjectConfig,
+) -> Result<Vec<String>> {
+    let workdir = repo
+        .workdir()
+        .ok_or(anyhow!("cannot deploy changes from a bare repository"))?
+        .canonicalize()?;
+    let project_dir = env::current_dir()?.canonicalize()?;
+    let prefix = project_dir.strip_prefix(&workdir).unwrap_or(Path::new(""));
+    Ok(project_config
+        .get_packages()
+        .iter()
+        .map(|x| prefix.join(&x.path).to_string_lossy().to_string())
+        .collect())
+}
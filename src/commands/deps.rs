@@ -0,0 +1,408 @@
+use crate::lock::{self, Lockfile, LOCK_PATH};
+use crate::project_config::SalesforceProjectConfig;
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use log::warn;
+use serde_json::json;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Format {
+    Text,
+    Dot,
+    Json,
+}
+
+// one package requiring another
+#[derive(Debug, Clone, PartialEq)]
+struct Edge {
+    from: String,
+    to: String,
+    // as written in sfdx-project.json, transitive dependencies have none
+    version_number: String,
+    // from ht.lock
+    version: String,
+    id: String,
+}
+
+#[derive(Debug, Default)]
+struct Graph {
+    // the packages of the project and their versions
+    roots: Vec<(String, String)>,
+    edges: Vec<Edge>,
+}
+
+impl Edge {
+    fn get_version(&self) -> &str {
+        if self.version.is_empty() {
+            &self.version_number
+        } else {
+            &self.version
+        }
+    }
+
+    fn get_major(&self) -> Option<u32> {
+        self.get_version().split('.').next()?.parse().ok()
+    }
+}
+
+impl Graph {
+    // the packages of the project depend on what sfdx-project.json says, the locked package
+    // versions on what their required-by says
+    fn from(project_config: &SalesforceProjectConfig, lockfile: Option<&Lockfile>) -> Graph {
+        let mut graph = Graph::default();
+        let locked = lockfile.map(|x| x.dependencies.as_slice()).unwrap_or(&[]);
+
+        for package in project_config.get_packages() {
+            graph
+                .roots
+                .push((package.name.clone(), package.version_number.clone()));
+            for dependency in package.dependencies.iter().flatten() {
+                let version_number = dependency.version_number.clone().unwrap_or_default();
+                let locked = locked.iter().find(|x| {
+                    x.required_by.is_empty()
+                        && x.name == dependency.name
+                        && x.version_number == version_number
+                });
+                graph.edges.push(Edge {
+                    from: package.name.clone(),
+                    to: dependency.name.clone(),
+                    version_number,
+                    version: locked.map(|x| x.version.clone()).unwrap_or_default(),
+                    id: locked
+                        .map(|x| x.id.clone())
+                        .unwrap_or(dependency.id.clone()),
+                });
+            }
+        }
+        for dependency in locked {
+            for parent in &dependency.required_by {
+                graph.edges.push(Edge {
+                    from: parent.clone(),
+                    to: dependency.name.clone(),
+                    version_number: String::new(),
+                    version: dependency.version.clone(),
+                    id: dependency.id.clone(),
+                });
+            }
+        }
+        graph
+    }
+
+    fn get_children(&self, name: &str) -> Vec<&Edge> {
+        self.edges.iter().filter(|x| x.from == name).collect()
+    }
+
+    // packages required with different major versions
+    fn get_conflicts(&self) -> BTreeMap<String, BTreeSet<u32>> {
+        let mut majors: BTreeMap<String, BTreeSet<u32>> = BTreeMap::new();
+        for edge in &self.edges {
+            if let Some(major) = edge.get_major() {
+                majors.entry(edge.to.clone()).or_default().insert(major);
+            }
+        }
+        majors.retain(|_, x| x.len() > 1);
+        majors
+    }
+
+    // every chain of requirements from a package of the project to the dependency
+    fn get_paths_to(&self, name: &str) -> Vec<Vec<&Edge>> {
+        let mut paths = Vec::new();
+        for (root, _) in &self.roots {
+            self.find_paths(root, name, &mut Vec::new(), &mut paths);
+        }
+        paths
+    }
+
+    fn find_paths<'a>(
+        &'a self,
+        from: &str,
+        name: &str,
+        path: &mut Vec<&'a Edge>,
+        paths: &mut Vec<Vec<&'a Edge>>,
+    ) {
+        for edge in self.get_children(from) {
+            // a cycle in the lockfile would never end
+            if path.iter().any(|x| x.from == edge.to) {
+                continue;
+            }
+            path.push(edge);
+            if edge.to == name {
+                paths.push(path.clone());
+            } else {
+                self.find_paths(&edge.to, name, path, paths);
+            }
+            path.pop();
+        }
+    }
+
+    // only the edges on the paths to the dependency
+    fn filter_to(&self, name: &str) -> Graph {
+        let paths = self.get_paths_to(name);
+        let mut edges: Vec<Edge> = Vec::new();
+        for edge in paths.iter().flatten() {
+            if !edges.contains(edge) {
+                edges.push((*edge).clone());
+            }
+        }
+        Graph {
+            roots: self
+                .roots
+                .iter()
+                .filter(|(root, _)| paths.iter().any(|x| &x[0].from == root))
+                .cloned()
+                .collect(),
+            edges,
+        }
+    }
+
+    fn to_text(&self) -> String {
+        let conflicts = self.get_conflicts();
+        let mut text = String::new();
+        for (root, version) in &self.roots {
+            text.push_str(&format!("{} {}\n", root, version));
+            self.write_children(root, "", &conflicts, &mut vec![root.as_str()], &mut text);
+        }
+        text
+    }
+
+    fn write_children<'a>(
+        &'a self,
+        name: &str,
+        prefix: &str,
+        conflicts: &BTreeMap<String, BTreeSet<u32>>,
+        ancestors: &mut Vec<&'a str>,
+        text: &mut String,
+    ) {
+        let children = self.get_children(name);
+        for (index, edge) in children.iter().enumerate() {
+            let is_last = index == children.len() - 1;
+            text.push_str(prefix);
+            text.push_str(if is_last { "└── " } else { "├── " });
+            text.push_str(&format_edge(edge, conflicts));
+            text.push('\n');
+            if ancestors.contains(&edge.to.as_str()) {
+                continue;
+            }
+            ancestors.push(&edge.to);
+            let prefix = format!("{}{}", prefix, if is_last { "    " } else { "│   " });
+            self.write_children(&edge.to, &prefix, conflicts, ancestors, text);
+            ancestors.pop();
+        }
+    }
+
+    fn to_dot(&self) -> String {
+        let conflicts = self.get_conflicts();
+        let mut dot = String::from("digraph dependencies {\n");
+        for (root, version) in &self.roots {
+            dot.push_str(&format!(
+                "  \"{}\" [label=\"{}\\n{}\", shape=box];\n",
+                escape(root),
+                escape(root),
+                escape(version)
+            ));
+        }
+        for name in conflicts.keys() {
+            if self.edges.iter().any(|x| &x.to == name) {
+                dot.push_str(&format!("  \"{}\" [color=red];\n", escape(name)));
+            }
+        }
+        for edge in &self.edges {
+            dot.push_str(&format!(
+                "  \"{}\" -> \"{}\" [label=\"{}\"{}];\n",
+                escape(&edge.from),
+                escape(&edge.to),
+                escape(edge.get_version()),
+                if conflicts.contains_key(&edge.to) {
+                    ", color=red"
+                } else {
+                    ""
+                }
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        json!({
+            "packages": self
+                .roots
+                .iter()
+                .map(|(name, version)| json!({ "name": name, "versionNumber": version }))
+                .collect::<Vec<_>>(),
+            "dependencies": self
+                .edges
+                .iter()
+                .map(|x| {
+                    json!({
+                        "from": x.from,
+                        "to": x.to,
+                        "versionNumber": x.version_number,
+                        "version": x.version,
+                        "id": x.id,
+                    })
+                })
+                .collect::<Vec<_>>(),
+            "conflicts": self
+                .get_conflicts()
+                .iter()
+                .map(|(name, majors)| json!({ "package": name, "majorVersions": majors }))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    fn print(&self, format: Format) -> Result<()> {
+        match format {
+            Format::Text => print!("{}", self.to_text()),
+            Format::Dot => print!("{}", self.to_dot()),
+            Format::Json => println!("{}", serde_json::to_string_pretty(&self.to_json())?),
+        }
+        Ok(())
+    }
+}
+
+fn format_edge(edge: &Edge, conflicts: &BTreeMap<String, BTreeSet<u32>>) -> String {
+    let mut label = edge.to.clone();
+    if !edge.version_number.is_empty() {
+        label.push_str(&format!(" {}", edge.version_number));
+    }
+    if !edge.version.is_empty() && edge.version != edge.version_number {
+        let arrow = if edge.version_number.is_empty() {
+            ""
+        } else {
+            "→ "
+        };
+        label.push_str(&format!(" {}{}", arrow, edge.version));
+    }
+    if !edge.id.is_empty() {
+        label.push_str(&format!(" ({})", edge.id));
+    }
+    if let Some(majors) = conflicts.get(&edge.to) {
+        label.push_str(&format!(
+            " [conflict: major versions {}]",
+            majors
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    label
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn read_graph(project_config: &SalesforceProjectConfig) -> Result<Graph> {
+    let lockfile = lock::read(Path::new(LOCK_PATH))?;
+    // logged so it stays out of the dot and json output
+    if lockfile.is_none() {
+        warn!(
+            "no {}, showing the versionNumbers from sfdx-project.json without their dependencies, run `ht lock` to resolve them",
+            LOCK_PATH
+        );
+    }
+    Ok(Graph::from(project_config, lockfile.as_ref()))
+}
+
+pub fn tree(format: Format, project_config: &SalesforceProjectConfig) -> Result<()> {
+    let graph = read_graph(project_config)?;
+    graph.print(format)?;
+    let conflicts = graph.get_conflicts();
+    if format == Format::Text && !conflicts.is_empty() {
+        println!(
+            "\n{} dependencies are required with different major versions",
+            conflicts.len()
+        );
+    }
+    Ok(())
+}
+
+pub fn why(name: &str, format: Format, project_config: &SalesforceProjectConfig) -> Result<()> {
+    let graph = read_graph(project_config)?;
+    let paths = graph.get_paths_to(name);
+    if paths.is_empty() {
+        return Err(anyhow!("{} is not a dependency of any package", name));
+    }
+    if format != Format::Text {
+        return graph.filter_to(name).print(format);
+    }
+
+    let conflicts = graph.get_conflicts();
+    for path in paths {
+        let mut chain = vec![path[0].from.clone()];
+        chain.extend(path.iter().map(|x| x.to.clone()));
+        println!(
+            "{}: {}",
+            chain.join(" → "),
+            format_edge(path[path.len() - 1], &conflicts)
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project_config;
+
+    fn read(path: &str, lock_path: Option<&str>) -> Graph {
+        let project_config = project_config::read(Some(path.to_owned())).unwrap();
+        let lockfile = lock_path.map(|x| lock::read(Path::new(x)).unwrap().unwrap());
+        Graph::from(&project_config, lockfile.as_ref())
+    }
+
+    #[test]
+    fn it_should_print_the_dependency_tree() {
+        let graph = read(
+            "tests/resources/sfdx-project-valid.json",
+            Some("tests/resources/ht.lock"),
+        );
+        assert_eq!(
+            "Test - Package 1.0.0.NEXT
+Expense Manager 3.2.0.NEXT
+├── A 1.0.0.LATEST (04tB00000000000000)
+└── B 2.0 (04tB00000000000001)
+    └── C 1.4.0.2 (04tB0000000000000C)
+",
+            graph.to_text()
+        );
+        assert!(graph.get_conflicts().is_empty());
+
+        let paths = graph.get_paths_to("C");
+        assert_eq!(1, paths.len());
+        assert_eq!(
+            vec!["B", "C"],
+            paths[0].iter().map(|x| x.to.as_str()).collect::<Vec<_>>()
+        );
+        let filtered = graph.filter_to("C");
+        assert_eq!(2, filtered.edges.len());
+        assert_eq!(1, filtered.roots.len());
+        assert!(filtered
+            .to_dot()
+            .contains("\"B\" -> \"C\" [label=\"1.4.0.2\"];"));
+    }
+
+    #[test]
+    fn it_should_highlight_major_version_conflicts() {
+        let graph = read("tests/resources/sfdx-project-deps.json", None);
+        let conflicts = graph.get_conflicts();
+        assert_eq!(
+            Some(&BTreeSet::from([3, 4])),
+            conflicts.get("Expense Manager")
+        );
+        assert!(graph
+            .to_text()
+            .contains("Expense Manager 4.0.0.RELEASED [conflict: major versions 3, 4]"));
+        assert!(graph.to_dot().contains("\"Expense Manager\" [color=red];"));
+        assert_eq!(
+            json!([{ "package": "Expense Manager", "majorVersions": [3, 4] }]),
+            graph.to_json()["conflicts"]
+        );
+    }
+}
@@ -0,0 +1,47 @@
+use crate::lint::{self, Severity};
+use anyhow::{anyhow, Result};
+use cli_table::{print_stdout, Cell, Style, Table};
+use serde_json::Value;
+use std::{fs, path::Path};
+
+pub fn run(path: &Path) -> Result<()> {
+    let document: Value = serde_json::from_str(&fs::read_to_string(path)?)
+        .map_err(|e| anyhow!("{} is not valid JSON: {}", path.display(), e))?;
+    let root = path.parent().unwrap_or(Path::new("."));
+    let findings = lint::lint(&document, Some(root));
+    if findings.is_empty() {
+        println!("{} has no problems", path.display());
+        return Ok(());
+    }
+
+    print_stdout(
+        findings
+            .iter()
+            .map(|x| {
+                vec![
+                    x.severity.cell(),
+                    x.path.clone().cell(),
+                    x.message.clone().cell(),
+                    x.hint.clone().cell(),
+                ]
+            })
+            .collect::<Vec<_>>()
+            .table()
+            .title(vec![
+                "Severity".cell().bold(true),
+                "Path".cell().bold(true),
+                "Problem".cell().bold(true),
+                "Fix".cell().bold(true),
+            ])
+            .bold(true),
+    )?;
+
+    let errors = findings
+        .iter()
+        .filter(|x| x.severity == Severity::Error)
+        .count();
+    if errors > 0 {
+        return Err(anyhow!("{} has {} errors", path.display(), errors));
+    }
+    Ok(())
+}
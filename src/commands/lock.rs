@@ -0,0 +1,29 @@
+use crate::lock::{self, LOCK_PATH};
+use crate::project_config::SalesforceProjectConfig;
+use anyhow::{anyhow, Result};
+use std::path::Path;
+
+pub fn run(devhub: &str, check: bool, project_config: &mut SalesforceProjectConfig) -> Result<()> {
+    let dependencies = project_config.get_dependencies().unwrap_or_default();
+    let path = Path::new(LOCK_PATH);
+    if check {
+        return match lock::read(path)? {
+            Some(lockfile) => {
+                lockfile.check(&dependencies)?;
+                println!("{} is up to date", LOCK_PATH);
+                Ok(())
+            }
+            None if dependencies.is_empty() => Ok(()),
+            None => Err(anyhow!("there is no {}, run `ht lock`", LOCK_PATH)),
+        };
+    }
+
+    let lockfile = lock::create(devhub, &dependencies)?;
+    lockfile.write(path)?;
+    println!(
+        "Locked {} dependencies in {}",
+        lockfile.dependencies.len(),
+        LOCK_PATH
+    );
+    Ok(())
+}
@@ -1,2 +1,10 @@
+pub mod alias;
+pub mod config;
+pub mod deploy;
+pub mod deps;
+pub mod lint_project;
+pub mod lock;
+pub mod org;
+pub mod validate;
 pub mod verify;
 pub mod version;
@@ -0,0 +1,297 @@
+use crate::cli::sf::{CliResult, SalesforceCli, SCRATCH_ORG_DESCRIPTION};
+use crate::project_config::SalesforceProjectConfig;
+use crate::state::{self, ScratchOrg, State};
+use anyhow::{anyhow, Result};
+use cli_table::{print_stdout, Cell, Style, Table};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+const ACTIVE_SCRATCH_ORG_QUERY: &str =
+    "SELECT Id, SignupUsername, Description, CreatedDate, CreatedBy.Name FROM ActiveScratchOrg ORDER BY CreatedDate";
+
+#[derive(Debug, PartialEq)]
+struct LeakedOrg {
+    id: String,
+    alias: Option<String>,
+    username: String,
+    created_by: String,
+    age: u64,
+}
+
+// deletes the scratch orgs created by ht that are older than max_age_hours
+pub fn prune(
+    devhub: &str,
+    max_age_hours: u64,
+    dry_run: bool,
+    project_config: &SalesforceProjectConfig,
+) -> Result<()> {
+    let mut cli = SalesforceCli::new(Some(devhub.to_owned()));
+    let records = match cli.query(ACTIVE_SCRATCH_ORG_QUERY, false)?.result {
+        Some(CliResult::Query { records, .. }) => records,
+        _ => {
+            return Err(anyhow!(
+                "could not query the active scratch orgs of {}",
+                devhub
+            ))
+        }
+    };
+    let aliases = match cli.list_orgs()?.result {
+        Some(CliResult::OrgList { scratch_orgs, .. }) => scratch_orgs
+            .into_iter()
+            .filter_map(|x| x.alias.map(|alias| (x.username.to_lowercase(), alias)))
+            .collect(),
+        _ => BTreeMap::new(),
+    };
+    let mut state = State::read()?;
+
+    let orgs = find_leaked_orgs(
+        &records,
+        &aliases,
+        state.get_scratch_orgs(),
+        project_config.get_name(),
+        state::now(),
+    );
+    let max_age = max_age_hours * 60 * 60;
+    let rows = orgs
+        .iter()
+        .map(|x| {
+            vec![
+                x.alias.clone().unwrap_or_default().cell(),
+                x.username.clone().cell(),
+                x.created_by.clone().cell(),
+                format_age(x.age).cell(),
+                if x.age >= max_age { "delete" } else { "keep" }.cell(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    print_stdout(
+        rows.table()
+            .title(vec![
+                "Alias".cell().bold(true),
+                "Username".cell().bold(true),
+                "Created By".cell().bold(true),
+                "Age".cell().bold(true),
+                "Action".cell().bold(true),
+            ])
+            .bold(true),
+    )?;
+
+    if dry_run {
+        return Ok(());
+    }
+    for org in orgs.iter().filter(|x| x.age >= max_age) {
+        cli.delete_record("ActiveScratchOrg", &org.id)?;
+        println!("Deleted scratch org {}", org.username);
+    }
+
+    let active = orgs
+        .iter()
+        .filter(|x| x.age < max_age)
+        .map(|x| x.username.to_lowercase())
+        .collect::<Vec<_>>();
+    for alias in find_stale_orgs(state.get_scratch_orgs(), &active, devhub) {
+        state.remove_scratch_org(&alias);
+    }
+    state.write()
+}
+
+// kept orgs of this Dev Hub that expired or were deleted are no longer active, the query says
+// nothing about the orgs of other Dev Hubs
+fn find_stale_orgs(
+    kept: &BTreeMap<String, ScratchOrg>,
+    active: &[String],
+    devhub: &str,
+) -> Vec<String> {
+    kept.iter()
+        .filter(|(_, x)| x.devhub == devhub && !active.contains(&x.username.to_lowercase()))
+        .map(|(alias, _)| alias.clone())
+        .collect()
+}
+
+// ht names scratch orgs after the project followed by a random number and puts the name in the
+// description, orgs created before that are only known by their local alias
+fn find_leaked_orgs(
+    records: &[Value],
+    aliases: &BTreeMap<String, String>,
+    kept: &BTreeMap<String, ScratchOrg>,
+    project_name: &str,
+    now: u64,
+) -> Vec<LeakedOrg> {
+    records
+        .iter()
+        .filter_map(|record| {
+            let username = record["SignupUsername"].as_str()?.to_owned();
+            let alias = record["Description"]
+                .as_str()
+                .and_then(|x| x.strip_prefix(SCRATCH_ORG_DESCRIPTION))
+                .map(str::to_owned)
+                .or_else(|| aliases.get(&username.to_lowercase()).cloned())
+                .or_else(|| {
+                    kept.iter()
+                        .find(|(_, x)| x.username.eq_ignore_ascii_case(&username))
+                        .map(|(alias, _)| alias.clone())
+                });
+            let is_ht_org = alias.as_deref().is_some_and(|x| {
+                x.strip_prefix(project_name)
+                    .is_some_and(|x| !x.is_empty() && x.chars().all(|x| x.is_ascii_digit()))
+                    || kept.contains_key(x)
+            });
+            if !is_ht_org {
+                return None;
+            }
+            let created_at = parse_datetime(record["CreatedDate"].as_str()?)?;
+            Some(LeakedOrg {
+                id: record["Id"].as_str()?.to_owned(),
+                alias,
+                username,
+                created_by: record["CreatedBy"]["Name"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_owned(),
+                age: now.saturating_sub(created_at),
+            })
+        })
+        .collect()
+}
+
+// Salesforce datetimes look like 2025-01-05T22:35:49.000+0000 and are in UTC
+fn parse_datetime(value: &str) -> Option<u64> {
+    let number = |range: std::ops::Range<usize>| value.get(range)?.parse::<i64>().ok();
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+
+    // days since 1970-01-01 in the proleptic gregorian calendar
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    u64::try_from(days * 86400 + hour * 3600 + minute * 60 + second).ok()
+}
+
+fn format_age(seconds: u64) -> String {
+    let hours = seconds / 3600;
+    if hours >= 24 {
+        format!("{}d {}h", hours / 24, hours % 24)
+    } else {
+        format!("{}h", hours)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_parse_salesforce_datetimes() {
+        assert_eq!(Some(0), parse_datetime("1970-01-01T00:00:00.000+0000"));
+        assert_eq!(
+            Some(1736116549),
+            parse_datetime("2025-01-05T22:35:49.000+0000")
+        );
+        assert_eq!(None, parse_datetime("2025-01-05"));
+        assert_eq!("2d 3h", format_age(2 * 86400 + 3 * 3600 + 59));
+    }
+
+    #[test]
+    fn it_should_find_the_scratch_orgs_created_by_ht() {
+        let records: Vec<Value> = serde_json::from_str(
+            r#"[
+  { "Id": "2SR000000000001", "SignupUsername": "test-abc@example.com", "CreatedDate": "2025-01-05T00:00:00.000+0000", "CreatedBy": { "Name": "CI" } },
+  { "Id": "2SR000000000002", "SignupUsername": "test-def@example.com", "CreatedDate": "2025-01-05T00:00:00.000+0000", "CreatedBy": { "Name": "Ann" } },
+  { "Id": "2SR000000000003", "SignupUsername": "test-ghi@example.com", "CreatedDate": "2025-01-05T00:00:00.000+0000", "CreatedBy": { "Name": "Ann" } },
+  { "Id": "2SR000000000004", "SignupUsername": "test-jkl@example.com", "CreatedDate": "2025-01-05T00:00:00.000+0000", "CreatedBy": { "Name": "Ann" } },
+  { "Id": "2SR000000000005", "SignupUsername": "test-mno@example.com", "Description": "Created by ht as project456", "CreatedDate": "2025-01-05T00:00:00.000+0000", "CreatedBy": { "Name": "CI" } },
+  { "Id": "2SR000000000006", "SignupUsername": "test-pqr@example.com", "Description": "Created by ht as other789", "CreatedDate": "2025-01-05T00:00:00.000+0000", "CreatedBy": { "Name": "CI" } }
+]"#,
+        )
+        .unwrap();
+        let aliases = BTreeMap::from([
+            (
+                String::from("test-abc@example.com"),
+                String::from("project123"),
+            ),
+            (
+                String::from("test-def@example.com"),
+                String::from("feature"),
+            ),
+            (
+                String::from("test-ghi@example.com"),
+                String::from("project-x"),
+            ),
+        ]);
+        let kept = BTreeMap::from([(
+            String::from("debugging"),
+            ScratchOrg {
+                username: String::from("test-jkl@example.com"),
+                devhub: String::from("DevHub"),
+                kept_at: 0,
+            },
+        )]);
+
+        let orgs = find_leaked_orgs(&records, &aliases, &kept, "project", 1736121600);
+        assert_eq!(
+            vec![
+                LeakedOrg {
+                    id: String::from("2SR000000000001"),
+                    alias: Some(String::from("project123")),
+                    username: String::from("test-abc@example.com"),
+                    created_by: String::from("CI"),
+                    age: 86400,
+                },
+                LeakedOrg {
+                    id: String::from("2SR000000000004"),
+                    alias: Some(String::from("debugging")),
+                    username: String::from("test-jkl@example.com"),
+                    created_by: String::from("Ann"),
+                    age: 86400,
+                },
+                // not in the local org list, e.g. created on a CI runner
+                LeakedOrg {
+                    id: String::from("2SR000000000005"),
+                    alias: Some(String::from("project456")),
+                    username: String::from("test-mno@example.com"),
+                    created_by: String::from("CI"),
+                    age: 86400,
+                },
+            ],
+            orgs
+        );
+    }
+
+    #[test]
+    fn it_should_only_forget_the_kept_orgs_of_the_pruned_devhub() {
+        let kept = BTreeMap::from([
+            (
+                String::from("debugging"),
+                ScratchOrg {
+                    username: String::from("test-jkl@example.com"),
+                    devhub: String::from("DevHub"),
+                    kept_at: 0,
+                },
+            ),
+            (
+                String::from("active"),
+                ScratchOrg {
+                    username: String::from("Test-Abc@example.com"),
+                    devhub: String::from("DevHub"),
+                    kept_at: 0,
+                },
+            ),
+            (
+                String::from("other"),
+                ScratchOrg {
+                    username: String::from("test-xyz@example.com"),
+                    devhub: String::from("OtherHub"),
+                    kept_at: 0,
+                },
+            ),
+        ]);
+        assert_eq!(
+            vec![String::from("debugging")],
+            find_stale_orgs(&kept, &[String::from("test-abc@example.com")], "DevHub")
+        );
+    }
+}
@@ -0,0 +1,41 @@
+use crate::approval;
+use crate::cli::sf::{CliResult, SalesforceCli};
+use crate::config::Settings;
+use crate::github;
+use crate::project_config::SalesforceProjectConfig;
+use crate::state::State;
+use anyhow::{anyhow, Result};
+
+pub fn run(
+    target_org: &str,
+    settings: &Settings,
+    approved: bool,
+    project_config: &SalesforceProjectConfig,
+) -> Result<()> {
+    approval::check(settings, "validate against", target_org, approved)?;
+    let paths = project_config
+        .get_packages()
+        .iter()
+        .map(|x| x.path.as_str())
+        .collect::<Vec<_>>();
+
+    let mut cli = SalesforceCli::new(Some(target_org.to_owned()));
+    cli.set_wait_minutes(settings.wait_minutes.value);
+    let command_output = cli.project_deploy_validate(&paths, &settings.test_level.value)?;
+    let job_id = match &command_output.result {
+        Some(CliResult::ProjectDeploy { id, .. }) if !id.is_empty() => id,
+        _ => return Err(anyhow!("validation did not return a job id")),
+    };
+    settings.check_coverage(command_output.get_coverage())?;
+
+    let mut state = State::read()?;
+    state.set_validation(target_org, job_id);
+    state.write()?;
+    github::set_output("validation-job-id", job_id)?;
+
+    println!(
+        "Validated deployment {}. Run `ht deploy -o {} --quick` to deploy it",
+        job_id, target_org
+    );
+    Ok(())
+}
@@ -1,48 +1,215 @@
-use crate::cli::sf::SalesforceCli;
-use crate::project;
+use crate::approval;
+use crate::cli::sf::{CliResult, SalesforceCli, ScratchOrgOptions};
+use crate::config::{HtConfig, Settings};
+use crate::github;
+use crate::lock;
+use crate::permissions;
+use crate::project::{HookCommand, HookContext, Hooks, Phase};
 use crate::project_config::SalesforceProjectConfig;
-use anyhow::Result;
+use crate::state::State;
+use crate::users;
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::{fs, path::Path};
+
+const DEFAULT_DEFINITION_FILE: &str = "config/project-scratch-def.json";
+
+pub struct VerifyOptions {
+    // approves verifying against the org of an environment that requires approval
+    pub approved: bool,
+    // resolves the dependencies again and rewrites ht.lock
+    pub update_lock: bool,
+}
 
 pub fn run(
     scratch_org_name: &String,
-    devhub: &Option<String>,
     target_org: &Option<String>,
+    scratch_options: &ScratchOrgOptions,
+    ht_config: &HtConfig,
+    settings: &Settings,
+    options: &VerifyOptions,
     project_config: &mut SalesforceProjectConfig,
 ) -> Result<()> {
-    let devhub_alias = match devhub {
-        Some(x) => x,
-        None => &String::from("DevHub"),
-    };
-
-    let mut cli: SalesforceCli;
+    let devhub_alias = &settings.devhub.value;
+    let org = target_org.to_owned().unwrap_or(scratch_org_name.to_owned());
+    if target_org.is_some() {
+        approval::check(settings, "verify against", &org, options.approved)?;
+    }
+    let mut cli = SalesforceCli::new(Some(org.to_owned()));
+    cli.set_wait_minutes(settings.wait_minutes.value);
     if target_org.is_none() {
-        cli = SalesforceCli::new(Some(scratch_org_name.to_owned()));
-        cli.create_scratch_org(devhub_alias)?;
-    } else {
-        cli = SalesforceCli::new(Some(target_org.to_owned().unwrap()));
+        let options = get_scratch_org_options(scratch_options, project_config)?;
+        cli.create_scratch_org(devhub_alias, &options)?;
     }
 
     if let Some(dependencies) = project_config.get_dependencies() {
-        for dependency in dependencies {
-            cli.install_package(dependency.id.as_str())?;
+        for id in lock::get_install_ids(devhub_alias, &dependencies, options.update_lock)? {
+            cli.install_package(id.as_str())?;
         }
     }
 
-    project::exec_predeploy_scripts(cli.to_owned())?;
+    let mut hooks = Hooks::load(
+        HookCommand::Verify,
+        HookContext {
+            target_org: org,
+            devhub: devhub_alias.to_owned(),
+        },
+        settings,
+        project_config.get_packages(),
+    )?;
+    hooks.run(Phase::Pre, None, &mut cli)?;
     // deploy metadata
     for package in project_config.get_packages() {
         if let Some(path) = &package.unpackaged_metadata {
-            cli.project_deploy(path.as_str())?;
+            cli.project_deploy(path.as_str(), None)?;
         }
     }
     for package in project_config.get_packages() {
-        cli.project_deploy(package.path.as_str())?;
+        hooks.run(Phase::Pre, Some(package), &mut cli)?;
+        cli.project_deploy(package.path.as_str(), None)?;
+        if let Some(settings) = ht_config.get_package_settings(&package.name) {
+            permissions::assign(&mut cli, settings)?;
+        }
+        hooks.run(Phase::Post, Some(package), &mut cli)?;
+    }
+
+    for user in users::create(&mut cli, &ht_config.users)? {
+        hooks.add_user(&user.alias, &user.username);
     }
 
-    project::exec_postdeploy_scripts(cli.to_owned())?;
+    hooks.run(Phase::Post, None, &mut cli)?;
 
     // run tests
-    cli.run_tests()?;
+    let command_output = cli.run_tests(&settings.test_level.value)?;
+    settings.check_coverage(command_output.get_coverage())?;
+
+    Ok(())
+}
 
+// kept orgs are recorded in the state so they can be pruned later
+pub fn keep_scratch_org(alias: &str, devhub: &str) -> Result<()> {
+    let mut cli = SalesforceCli::new(Some(alias.to_owned()));
+    let command_output = cli.open_org()?;
+    let Some(CliResult::OpenOrg { url, username, .. }) = &command_output.result else {
+        return Err(anyhow!("could not get a login url for {}", alias));
+    };
+
+    let mut state = State::read()?;
+    state.add_scratch_org(alias, username, devhub);
+    state.write()?;
+
+    println!("Kept scratch org {} ({})", alias, username);
+    // the url carries a session id, which must not end up in a CI log
+    github::mask(url);
+    println!("Log in with {}", url);
     Ok(())
 }
+
+// every package is deployed to the same scratch org, so --definition-file wins and the packages
+// have to agree on their definitionFile otherwise
+fn get_scratch_org_options(
+    scratch_options: &ScratchOrgOptions,
+    project_config: &SalesforceProjectConfig,
+) -> Result<ScratchOrgOptions> {
+    let definition_file = match &scratch_options.definition_file {
+        Some(x) => x.to_owned(),
+        None => {
+            let declared = project_config
+                .get_packages()
+                .iter()
+                .filter_map(|x| x.definition_file.as_ref().map(|file| (&x.name, file)))
+                .collect::<Vec<_>>();
+            match declared.first() {
+                Some((_, file)) if declared.iter().all(|x| x.1 == *file) => file.to_string(),
+                Some(_) => {
+                    return Err(anyhow!(
+                        "the packages have different definitionFiles ({}), pass --definition-file to choose one",
+                        declared
+                            .iter()
+                            .map(|(name, file)| format!("{}: {}", name, file))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ))
+                }
+                None => String::from(DEFAULT_DEFINITION_FILE),
+            }
+        }
+    };
+    check_definition_file(Path::new(&definition_file))?;
+
+    Ok(ScratchOrgOptions {
+        definition_file: Some(definition_file),
+        ..scratch_options.clone()
+    })
+}
+
+fn check_definition_file(path: &Path) -> Result<()> {
+    let content = fs::read_to_string(path).map_err(|e| {
+        anyhow!(
+            "could not read scratch org definition {}: {}",
+            path.display(),
+            e
+        )
+    })?;
+    match serde_json::from_str::<Value>(&content) {
+        Ok(Value::Object(_)) => Ok(()),
+        Ok(_) => Err(anyhow!(
+            "scratch org definition {} is not a JSON object",
+            path.display()
+        )),
+        Err(e) => Err(anyhow!(
+            "scratch org definition {} is not valid JSON: {}",
+            path.display(),
+            e
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project_config;
+
+    #[test]
+    fn it_should_use_the_definition_file_of_the_packages() {
+        let mut project_config = project_config::read(Some(String::from(
+            "tests/resources/sfdx-project-valid.json",
+        )))
+        .unwrap();
+        let mut scratch_options = ScratchOrgOptions {
+            definition_file: None,
+            duration_days: Some(3),
+            edition: None,
+            no_namespace: true,
+        };
+        let error = get_scratch_org_options(&scratch_options, &project_config).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("could not read scratch org definition config/scratch-org-def.json"));
+
+        project_config
+            .get_package("Expense Manager")
+            .unwrap()
+            .definition_file = Some(String::from("config/other-def.json"));
+        assert_eq!(
+            "the packages have different definitionFiles (Test - Package: config/scratch-org-def.json, Expense Manager: config/other-def.json), pass --definition-file to choose one",
+            get_scratch_org_options(&scratch_options, &project_config)
+                .unwrap_err()
+                .to_string()
+        );
+
+        scratch_options.definition_file = Some(String::from(
+            "tests/resources/fake-salesforce-project/config/project-scratch-def.json",
+        ));
+        let options = get_scratch_org_options(&scratch_options, &project_config).unwrap();
+        assert_eq!(Some(3), options.duration_days);
+        assert!(options.no_namespace);
+    }
+
+    #[test]
+    fn it_should_reject_invalid_definition_files() {
+        let error = check_definition_file(Path::new("tests/resources/invalid-scratch-def.json"))
+            .unwrap_err();
+        assert!(error.to_string().contains("is not valid JSON"));
+    }
+}
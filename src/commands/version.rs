@@ -1,26 +1,28 @@
 use anyhow::{anyhow, Result};
 use git2::{IndexAddOption, Repository};
-use indexmap::IndexMap;
-use serde_json::{json, Value};
-use std::{fs, io::Write};
+use std::collections::BTreeMap;
 
 use crate::{
-    cli::sf::SalesforceCli,
+    cli::sf::{CliResult, SalesforceCli},
+    config::Settings,
+    github,
     project_config::{Package, SalesforceProjectConfig, Version},
+    template,
 };
 
 pub fn run(
     project_config: &mut SalesforceProjectConfig,
     dry_run: &bool,
     push: &bool,
-    devhub: &Option<String>,
+    settings: &Settings,
 ) -> Result<()> {
-    if !dry_run && devhub.is_none() {
-        return Err(anyhow!("devhub is required"));
-    }
-
-    let repo = Repository::open(".").unwrap();
-    let message = get_latest_commit_message(&repo);
+    let repo = Repository::discover(".").map_err(|e| {
+        anyhow!(
+            "ht version needs the project to be in a git repository: {}",
+            e
+        )
+    })?;
+    let message = get_latest_commit_message(&repo)?;
 
     let commit_message_split = &message.split(':').collect::<Vec<&str>>();
     let commit_prefix = commit_message_split[0];
@@ -32,65 +34,61 @@ pub fn run(
     };
 
     let commit_prefix = commit_message_split[0].split('(').collect::<Vec<&str>>()[0];
-    let current_version = Version::from(&to_upgrade.version_number);
+    let current_version = Version::parse(&to_upgrade.version_number).ok_or(anyhow!(
+        "{} is not a valid version number for {}",
+        to_upgrade.version_number,
+        to_upgrade.name
+    ))?;
     let mut new_version = current_version;
     bump_version(commit_prefix, &mut new_version);
     to_upgrade.set_version(&new_version);
+    let upgraded_package = to_upgrade.name.clone();
 
     if new_version.is_higher_than(&current_version) {
-        let json_string = generate_new_sfdx_project(to_upgrade, new_version)?;
-        write_to_file(json_string)?;
+        // render both before anything is written so a bad template does not leave a half release
+        let variables = BTreeMap::from([
+            (String::from("package"), upgraded_package.clone()),
+            (String::from("version"), new_version.to_string()),
+        ]);
+        let commit_message = template::render(&settings.commit_message.value, &variables)?;
+        let tag = template::render(&settings.tag_format.value, &variables)?;
+        project_config.write()?;
 
         if !dry_run {
             let mut cli = SalesforceCli::new(None);
-            cli.create_package_version(devhub.as_ref().unwrap())?;
+            cli.set_wait_minutes(settings.wait_minutes.value);
+            let command_output = cli.create_package_version(&settings.devhub.value)?;
+            if let Some(CliResult::CreatePackageVersion {
+                subscriber_package_version_id: Some(version_id),
+                ..
+            }) = &command_output.result
+            {
+                github::set_output("package-version-id", version_id)?;
+                github::append_step_summary(&format!(
+                    "### Package Version\n\n{}",
+                    github::markdown_table(
+                        &["Package", "Version", "Subscriber Package Version Id"],
+                        vec![vec![
+                            upgraded_package.clone(),
+                            new_version.to_string(),
+                            version_id.clone(),
+                        ]],
+                    )
+                ))?;
+            }
         }
+        github::set_output("package-version-number", &new_version.to_string())?;
 
-        create_commit(&repo)?;
-        tag_commit(&repo, &new_version)?;
+        create_commit(&repo, &commit_message)?;
+        tag_commit(&repo, &tag, &new_version)?;
 
         if *push {
-            let mut origin = repo.find_remote("origin")?;
-            origin.push(&[String::new()], None);
+            push_release(&repo, &tag)?;
         }
     }
     Ok(())
 }
 
-fn write_to_file(json_string: String) -> Result<(), anyhow::Error> {
-    let mut f = fs::OpenOptions::new()
-        .write(true)
-        .truncate(true)
-        .open("./sfdx-project.json")
-        .expect("should have opened the sfdx project file");
-    f.write_all(&json_string.into_bytes())
-        .expect("should have overwrote the sfdx project json file");
-    f.flush()?;
-    Ok(())
-}
-
-fn generate_new_sfdx_project(
-    to_upgrade: &mut Package,
-    new_version: Version,
-) -> Result<String, anyhow::Error> {
-    let project_json_path = String::from("./sfdx-project.json");
-    let file_as_string = fs::read_to_string(project_json_path)?;
-    let mut config: IndexMap<String, Value> = serde_json::from_str(&file_as_string)?;
-    for package_dir in config
-        .get_mut("packageDirectories")
-        .unwrap()
-        .as_array_mut()
-        .unwrap()
-    {
-        if package_dir.get_mut("package").unwrap().as_str().unwrap() == to_upgrade.name {
-            let version_number = package_dir.get_mut("versionNumber").unwrap();
-            *version_number = json!(new_version.to_string());
-        }
-    }
-    let json_string = serde_json::to_string_pretty(&config)?;
-    Ok(json_string)
-}
-
 fn bump_version(commit_prefix: &str, new_version: &mut Version) {
     if commit_prefix.contains("!") {
         new_version.major += 1;
@@ -112,14 +110,12 @@ fn get_package_name_from_commit(commit_prefix: &str) -> String {
     package_name.to_string()
 }
 
-fn get_latest_commit_message(repo: &Repository) -> String {
-    let head = repo.head().unwrap();
-    let oid = head.target().unwrap();
-    let commit = repo.find_commit(oid).unwrap();
-    commit.message().unwrap().to_string()
+fn get_latest_commit_message(repo: &Repository) -> Result<String> {
+    let commit = repo.head()?.peel_to_commit()?;
+    Ok(commit.message().unwrap_or_default().to_string())
 }
 
-fn create_commit(repo: &Repository) -> Result<()> {
+fn create_commit(repo: &Repository, message: &str) -> Result<()> {
     // stage changes
     let mut index = repo.index().unwrap();
     index.add_all(["."], IndexAddOption::DEFAULT, None)?;
@@ -133,23 +129,72 @@ fn create_commit(repo: &Repository) -> Result<()> {
         Some("HEAD"),
         &signature,
         &signature,
-        "ci: making new version",
+        message,
         &tree,
         &[&parent_commit],
     )?;
     Ok(())
 }
 
-fn tag_commit(repo: &Repository, version: &Version) -> Result<()> {
+// pushes the current branch and the new tag, not every local tag
+fn push_release(repo: &Repository, tag: &str) -> Result<()> {
+    let head = repo.head()?;
+    let branch = match head.name() {
+        Some(name) if head.is_branch() => name,
+        _ => return Err(anyhow!("cannot push the release from a detached HEAD")),
+    };
+    let mut origin = repo.find_remote("origin")?;
+    origin
+        .push(&get_refspecs(branch, tag), None)
+        .map_err(|e| anyhow!("could not push {} and tag {}: {}", branch, tag, e))?;
+    Ok(())
+}
+
+fn get_refspecs(branch: &str, tag: &str) -> Vec<String> {
+    vec![
+        format!("{}:{}", branch, branch),
+        format!("refs/tags/{}:refs/tags/{}", tag, tag),
+    ]
+}
+
+fn tag_commit(repo: &Repository, name: &str, version: &Version) -> Result<()> {
     let sig = repo.signature()?;
     let obj = repo.revparse_single("HEAD")?;
-    repo.tag(
-        &version.to_string(),
-        &obj,
-        &sig,
-        &version.to_string(),
-        false,
-    )?;
+    repo.tag(name, &obj, &sig, &version.to_string(), false)?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_only_release_a_higher_version() {
+        let current_version = Version::parse("1.9.0").unwrap();
+        let mut new_version = current_version;
+        bump_version("fix", &mut new_version);
+        assert_eq!("1.9.1", new_version.to_string());
+        assert!(new_version.is_higher_than(&current_version));
+
+        let mut new_version = current_version;
+        bump_version("chore", &mut new_version);
+        assert!(!new_version.is_higher_than(&current_version));
+
+        // a higher major wins over a lower minor
+        let major = Version::parse("2.0.0").unwrap();
+        assert!(major.is_higher_than(&current_version));
+        assert!(!current_version.is_higher_than(&major));
+    }
+
+    #[test]
+    fn it_should_push_the_branch_and_the_release_tag() {
+        assert_eq!(
+            vec![
+                "refs/heads/main:refs/heads/main",
+                "refs/tags/v1.2.0:refs/tags/v1.2.0"
+            ],
+            get_refspecs("refs/heads/main", "v1.2.0")
+        );
+    }
+}
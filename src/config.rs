@@ -0,0 +1,527 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    fs,
+    path::Path,
+    str::FromStr,
+};
+
+use crate::project_config::{SalesforceProjectConfig, PROJECT_FILE};
+
+pub const CONFIG_PATH: &str = "ht.toml";
+// the ht section of the sfdx-project.json plugins, used when there is no ht.toml
+const PLUGIN_NAME: &str = "ht";
+
+// ht's own settings, next to sfdx-project.json
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct HtConfig {
+    devhub: Option<String>,
+    test_level: Option<String>,
+    script_manifest: Option<String>,
+    pre_scripts_dir: Option<String>,
+    post_scripts_dir: Option<String>,
+    commit_message: Option<String>,
+    tag_format: Option<String>,
+    wait_minutes: Option<u64>,
+    coverage_threshold: Option<u8>,
+    allow_destructive: Option<bool>,
+    require_approval: Option<bool>,
+    // selected with --env, their settings win over the ones above
+    environments: BTreeMap<String, EnvironmentSettings>,
+    packages: BTreeMap<String, PackageSettings>,
+    // created by verify after the packages are deployed
+    pub users: Vec<UserDefinition>,
+    // the file the settings were read from
+    #[serde(skip)]
+    path: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct EnvironmentSettings {
+    target_org: Option<String>,
+    test_level: Option<String>,
+    coverage_threshold: Option<u8>,
+    allow_destructive: Option<bool>,
+    require_approval: Option<bool>,
+    script_manifest: Option<String>,
+    pre_scripts_dir: Option<String>,
+    post_scripts_dir: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PackageSettings {
+    // assigned to the target org user after the package is deployed
+    pub permission_sets: Vec<String>,
+    pub permission_set_groups: Vec<String>,
+    pub permission_set_licenses: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct UserDefinition {
+    pub alias: String,
+    pub profile: String,
+    #[serde(default)]
+    pub permission_sets: Vec<String>,
+    pub locale: Option<String>,
+    // writes the username and a generated password to .ht/users.json
+    #[serde(default)]
+    pub save_credentials: bool,
+}
+
+// where the effective value of a setting came from
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    Default,
+    File(String),
+    Profile { file: String, environment: String },
+    Variable(String),
+    Flag(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Setting<T> {
+    pub value: T,
+    pub source: Source,
+}
+
+// the pipeline settings, layered as defaults, then the config file, then the selected
+// environment, then HT_CONFIG_* environment variables, then the flags of the command
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub environment: Option<String>,
+    pub devhub: Setting<String>,
+    pub target_org: Setting<Option<String>>,
+    pub test_level: Setting<String>,
+    // minimum org wide Apex code coverage in percent
+    pub coverage_threshold: Setting<Option<u8>>,
+    // None asks before deleting from a production org
+    pub allow_destructive: Setting<Option<bool>>,
+    pub require_approval: Setting<bool>,
+    pub script_manifest: Setting<String>,
+    pub pre_scripts_dir: Setting<String>,
+    pub post_scripts_dir: Setting<String>,
+    // templates with {{ package }} and {{ version }}
+    pub commit_message: Setting<String>,
+    pub tag_format: Setting<String>,
+    pub wait_minutes: Setting<u64>,
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::File(path) => write!(f, "{}", path),
+            Source::Profile { file, environment } => {
+                write!(f, "{} [environments.{}]", file, environment)
+            }
+            Source::Variable(name) => write!(f, "${}", name),
+            Source::Flag(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl<T> Setting<T> {
+    pub fn set_flag(&mut self, value: Option<T>, flag: &str) {
+        if let Some(value) = value {
+            self.value = value;
+            self.source = Source::Flag(flag.to_owned());
+        }
+    }
+}
+
+impl Settings {
+    // name, value and source of every setting, in the order they are documented
+    pub fn list(&self) -> Vec<(&'static str, String, &Source)> {
+        vec![
+            ("devhub", self.devhub.value.clone(), &self.devhub.source),
+            (
+                "target-org",
+                self.target_org.value.clone().unwrap_or_default(),
+                &self.target_org.source,
+            ),
+            (
+                "test-level",
+                self.test_level.value.clone(),
+                &self.test_level.source,
+            ),
+            (
+                "coverage-threshold",
+                self.coverage_threshold
+                    .value
+                    .map(|x| x.to_string())
+                    .unwrap_or_default(),
+                &self.coverage_threshold.source,
+            ),
+            (
+                "allow-destructive",
+                self.allow_destructive
+                    .value
+                    .map(|x| x.to_string())
+                    .unwrap_or_default(),
+                &self.allow_destructive.source,
+            ),
+            (
+                "require-approval",
+                self.require_approval.value.to_string(),
+                &self.require_approval.source,
+            ),
+            (
+                "script-manifest",
+                self.script_manifest.value.clone(),
+                &self.script_manifest.source,
+            ),
+            (
+                "pre-scripts-dir",
+                self.pre_scripts_dir.value.clone(),
+                &self.pre_scripts_dir.source,
+            ),
+            (
+                "post-scripts-dir",
+                self.post_scripts_dir.value.clone(),
+                &self.post_scripts_dir.source,
+            ),
+            (
+                "commit-message",
+                self.commit_message.value.clone(),
+                &self.commit_message.source,
+            ),
+            (
+                "tag-format",
+                self.tag_format.value.clone(),
+                &self.tag_format.source,
+            ),
+            (
+                "wait-minutes",
+                self.wait_minutes.value.to_string(),
+                &self.wait_minutes.source,
+            ),
+        ]
+    }
+
+    pub fn check_coverage(&self, coverage: Option<f64>) -> Result<()> {
+        let Some(threshold) = self.coverage_threshold.value else {
+            return Ok(());
+        };
+        match coverage {
+            Some(x) if x >= f64::from(threshold) => Ok(()),
+            Some(x) => Err(anyhow!(
+                "code coverage of {:.0}% is below the threshold of {}%",
+                x,
+                threshold
+            )),
+            None => Err(anyhow!(
+                "a coverage threshold of {}% is set but no tests reported coverage",
+                threshold
+            )),
+        }
+    }
+
+    // validate and deploy need an org, from the flag or the environment
+    pub fn get_target_org(&self) -> Result<String> {
+        match &self.target_org.value {
+            Some(x) => Ok(x.to_owned()),
+            None => Err(anyhow!(
+                "no target org, pass --target-org or select an environment with one using --env"
+            )),
+        }
+    }
+}
+
+impl HtConfig {
+    pub fn get_package_settings(&self, name: &str) -> Option<&PackageSettings> {
+        self.packages.get(name)
+    }
+
+    // env looks up environment variables, it is a parameter so tests do not depend on the process
+    pub fn get_settings(
+        &self,
+        environment: Option<&str>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Settings> {
+        let profile = match environment {
+            Some(name) => Some(self.environments.get(name).ok_or_else(|| {
+                anyhow!(
+                    "environment {} is not defined, the environments are: {}",
+                    name,
+                    self.environments
+                        .keys()
+                        .cloned()
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })?),
+            None => None,
+        };
+        let file = self.path.clone().unwrap_or(String::from(CONFIG_PATH));
+        let layers = Layers {
+            file: Source::File(file.clone()),
+            profile: Source::Profile {
+                file,
+                environment: environment.unwrap_or_default().to_owned(),
+            },
+            env,
+        };
+
+        Ok(Settings {
+            environment: environment.map(|x| x.to_owned()),
+            devhub: layers
+                .get("devhub", self.devhub.as_ref(), None)?
+                .unwrap_or(default(String::from("DevHub"))),
+            target_org: optional(layers.get(
+                "target-org",
+                None,
+                profile.and_then(|x| x.target_org.as_ref()),
+            )?),
+            test_level: layers
+                .get(
+                    "test-level",
+                    self.test_level.as_ref(),
+                    profile.and_then(|x| x.test_level.as_ref()),
+                )?
+                .unwrap_or(default(String::from("RunLocalTests"))),
+            coverage_threshold: optional(layers.get(
+                "coverage-threshold",
+                self.coverage_threshold.as_ref(),
+                profile.and_then(|x| x.coverage_threshold.as_ref()),
+            )?),
+            allow_destructive: optional(layers.get(
+                "allow-destructive",
+                self.allow_destructive.as_ref(),
+                profile.and_then(|x| x.allow_destructive.as_ref()),
+            )?),
+            require_approval: layers
+                .get(
+                    "require-approval",
+                    self.require_approval.as_ref(),
+                    profile.and_then(|x| x.require_approval.as_ref()),
+                )?
+                .unwrap_or(default(false)),
+            script_manifest: layers
+                .get(
+                    "script-manifest",
+                    self.script_manifest.as_ref(),
+                    profile.and_then(|x| x.script_manifest.as_ref()),
+                )?
+                .unwrap_or(default(String::from("deploy/scripts.json"))),
+            pre_scripts_dir: layers
+                .get(
+                    "pre-scripts-dir",
+                    self.pre_scripts_dir.as_ref(),
+                    profile.and_then(|x| x.pre_scripts_dir.as_ref()),
+                )?
+                .unwrap_or(default(String::from("deploy/pre"))),
+            post_scripts_dir: layers
+                .get(
+                    "post-scripts-dir",
+                    self.post_scripts_dir.as_ref(),
+                    profile.and_then(|x| x.post_scripts_dir.as_ref()),
+                )?
+                .unwrap_or(default(String::from("deploy/post"))),
+            commit_message: layers
+                .get("commit-message", self.commit_message.as_ref(), None)?
+                .unwrap_or(default(String::from("ci: making new version"))),
+            tag_format: layers
+                .get("tag-format", self.tag_format.as_ref(), None)?
+                .unwrap_or(default(String::from("{{ version }}"))),
+            wait_minutes: layers
+                .get("wait-minutes", self.wait_minutes.as_ref(), None)?
+                .unwrap_or(default(60)),
+        })
+    }
+}
+
+// not HT_ like the variables deployment scripts get, so ht run from a script does not take the
+// parent's HT_DEVHUB or HT_TARGET_ORG as settings
+const VARIABLE_PREFIX: &str = "HT_CONFIG_";
+
+struct Layers<E> {
+    file: Source,
+    profile: Source,
+    env: E,
+}
+
+impl<E: Fn(&str) -> Option<String>> Layers<E> {
+    // HT_CONFIG_* environment variables win over the environment, which wins over the file
+    fn get<T>(
+        &self,
+        name: &str,
+        file_value: Option<&T>,
+        profile_value: Option<&T>,
+    ) -> Result<Option<Setting<T>>>
+    where
+        T: FromStr + Clone,
+        T::Err: Display,
+    {
+        let variable = format!(
+            "{}{}",
+            VARIABLE_PREFIX,
+            name.to_uppercase().replace('-', "_")
+        );
+        if let Some(value) = (self.env)(&variable) {
+            return match value.parse() {
+                Ok(value) => Ok(Some(Setting {
+                    value,
+                    source: Source::Variable(variable),
+                })),
+                Err(e) => Err(anyhow!("{} is not a valid {}: {}", variable, name, e)),
+            };
+        }
+        Ok(match (profile_value, file_value) {
+            (Some(value), _) => Some(Setting {
+                value: value.clone(),
+                source: self.profile.clone(),
+            }),
+            (None, Some(value)) => Some(Setting {
+                value: value.clone(),
+                source: self.file.clone(),
+            }),
+            (None, None) => None,
+        })
+    }
+}
+
+fn default<T>(value: T) -> Setting<T> {
+    Setting {
+        value,
+        source: Source::Default,
+    }
+}
+
+fn optional<T>(setting: Option<Setting<T>>) -> Setting<Option<T>> {
+    match setting {
+        Some(x) => Setting {
+            value: Some(x.value),
+            source: x.source,
+        },
+        None => default(None),
+    }
+}
+
+// ht.toml wins over the ht section of the sfdx-project.json plugins
+pub fn load(project_config: &SalesforceProjectConfig) -> Result<HtConfig> {
+    let path = Path::new(CONFIG_PATH);
+    if path.exists() {
+        return read(path);
+    }
+    match project_config.get_plugin_settings(PLUGIN_NAME) {
+        Some(settings) => {
+            let mut config: HtConfig = serde_json::from_value(settings.clone()).map_err(|e| {
+                anyhow!(
+                    "plugins.{} in {} is not in expected format: {}",
+                    PLUGIN_NAME,
+                    PROJECT_FILE,
+                    e
+                )
+            })?;
+            config.path = Some(String::from(PROJECT_FILE));
+            Ok(config)
+        }
+        None => Ok(HtConfig::default()),
+    }
+}
+
+pub fn read(path: &Path) -> Result<HtConfig> {
+    if !path.exists() {
+        return Ok(HtConfig::default());
+    }
+    let mut config: HtConfig = toml::from_str(&fs::read_to_string(path)?)
+        .map_err(|e| anyhow!("{} is not in expected format: {}", path.display(), e))?;
+    config.path = Some(path.display().to_string());
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_read_the_package_settings() {
+        let config = read(Path::new("tests/resources/ht.toml")).unwrap();
+        let settings = config.get_package_settings("Expense Manager").unwrap();
+        assert_eq!(vec!["Expense_Admin"], settings.permission_sets);
+        assert_eq!(vec!["Expense_Approvers"], settings.permission_set_groups);
+        assert_eq!(
+            vec!["SalesforcePlatformPSL"],
+            settings.permission_set_licenses
+        );
+        assert!(config.get_package_settings("Test - Package").is_none());
+
+        assert_eq!(1, config.users.len());
+        assert_eq!("approver", config.users[0].alias);
+        assert_eq!(Some(String::from("fr_FR")), config.users[0].locale);
+        assert!(config.users[0].save_credentials);
+
+        assert!(read(Path::new("tests/resources/missing.toml"))
+            .unwrap()
+            .packages
+            .is_empty());
+    }
+
+    #[test]
+    fn it_should_layer_the_settings() {
+        let config = read(Path::new("tests/resources/ht.toml")).unwrap();
+        let mut settings = config
+            .get_settings(Some("qa"), |x| match x {
+                "HT_CONFIG_WAIT_MINUTES" => Some(String::from("30")),
+                // what ht passes to deployment scripts is not a setting
+                "HT_DEVHUB" => Some(String::from("ScriptHub")),
+                _ => None,
+            })
+            .unwrap();
+        settings
+            .devhub
+            .set_flag(Some(String::from("OtherHub")), "--devhub");
+        settings.target_org.set_flag(None, "--target-org");
+
+        let found = settings
+            .list()
+            .into_iter()
+            .map(|(name, value, source)| (name, value, source.to_string()))
+            .collect::<Vec<_>>();
+        let file = "tests/resources/ht.toml";
+        let profile = "tests/resources/ht.toml [environments.qa]";
+        assert_eq!(
+            vec![
+                ("devhub", "OtherHub", "--devhub"),
+                ("target-org", "qa-sandbox", profile),
+                ("test-level", "RunLocalTests", profile),
+                ("coverage-threshold", "80", profile),
+                ("allow-destructive", "false", file),
+                ("require-approval", "false", "default"),
+                ("script-manifest", "deploy/scripts.json", "default"),
+                ("pre-scripts-dir", "deploy/pre", "default"),
+                ("post-scripts-dir", "deploy/qa/post", profile),
+                ("commit-message", "ci: making new version", "default"),
+                ("tag-format", "v{{ version }}", file),
+                ("wait-minutes", "30", "$HT_CONFIG_WAIT_MINUTES"),
+            ],
+            found
+                .iter()
+                .map(|(name, value, source)| (*name, value.as_str(), source.as_str()))
+                .collect::<Vec<_>>()
+        );
+
+        let settings = config.get_settings(None, |_| None).unwrap();
+        assert_eq!(None, settings.target_org.value);
+        assert_eq!(Source::Default, settings.coverage_threshold.source);
+        assert!(settings.get_target_org().is_err());
+        assert!(settings.check_coverage(None).is_ok());
+
+        let settings = config.get_settings(Some("qa"), |_| None).unwrap();
+        assert!(settings.check_coverage(Some(80.0)).is_ok());
+        assert!(settings.check_coverage(Some(79.5)).is_err());
+        assert!(settings.check_coverage(None).is_err());
+
+        assert!(config
+            .get_settings(None, |x| (x == "HT_CONFIG_WAIT_MINUTES")
+                .then(|| String::from("soon")))
+            .is_err());
+        assert!(config.get_settings(Some("prod"), |_| None).is_err());
+    }
+}
@@ -0,0 +1,160 @@
+use anyhow::Result;
+use git2::{Delta as FileStatus, Repository, Tree};
+use std::{collections::BTreeSet, path::Path};
+
+use crate::metadata::{self, Component};
+
+#[derive(Debug, Default)]
+pub struct Delta {
+    pub changed: BTreeSet<Component>,
+    pub deleted: BTreeSet<Component>,
+}
+
+impl Delta {
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.deleted.is_empty()
+    }
+}
+
+// works out which components in the given package directories changed between the two git refs
+pub fn between(repo: &Repository, from: &str, to: &str, package_paths: &[&str]) -> Result<Delta> {
+    let old_tree = repo.revparse_single(from)?.peel_to_tree()?;
+    let new_tree = repo.revparse_single(to)?.peel_to_tree()?;
+    let diff = repo.diff_tree_to_tree(Some(&old_tree), Some(&new_tree), None)?;
+
+    let mut delta = Delta::default();
+    for file in diff.deltas() {
+        let old_path = file.old_file().path();
+        let new_path = file.new_file().path();
+        let (path, status) = match file.status() {
+            FileStatus::Deleted => (old_path, FileStatus::Deleted),
+            FileStatus::Renamed => {
+                if let Some(source) = old_path.and_then(|x| resolve(x, package_paths)) {
+                    if !exists(&new_tree, &source.root) {
+                        delta.deleted.insert(source.component);
+                    }
+                }
+                (new_path, FileStatus::Modified)
+            }
+            status => (new_path, status),
+        };
+
+        let Some(source) = path.and_then(|x| resolve(x, package_paths)) else {
+            continue;
+        };
+        // a deleted file only deletes its component when nothing else describes it anymore
+        if status == FileStatus::Deleted && !exists(&new_tree, &source.root) {
+            delta.deleted.insert(source.component);
+        } else {
+            delta.changed.insert(source.component);
+        }
+    }
+
+    delta.deleted.retain(|x| !delta.changed.contains(x));
+    Ok(delta)
+}
+
+fn exists(tree: &Tree, path: &Path) -> bool {
+    tree.get_path(path).is_ok()
+}
+
+fn resolve(path: &Path, package_paths: &[&str]) -> Option<metadata::SourceComponent> {
+    if !package_paths.iter().any(|x| path.starts_with(x)) {
+        return None;
+    }
+    metadata::resolve(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{IndexAddOption, Signature};
+    use std::fs;
+
+    fn commit_all(repo: &Repository, message: &str) {
+        let mut index = repo.index().unwrap();
+        index.add_all(["."], IndexAddOption::DEFAULT, None).unwrap();
+        index.update_all(["."], None).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("ht", "ht@example.com").unwrap();
+        let parent = repo.head().ok().map(|x| x.peel_to_commit().unwrap());
+        let parents = parent.iter().collect::<Vec<_>>();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+        .unwrap();
+    }
+
+    fn write(dir: &Path, path: &str, contents: &str) {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn it_should_find_changed_and_deleted_components() {
+        let dir = std::env::temp_dir().join(format!("ht-delta-{}", rand::random::<u64>()));
+        let repo = Repository::init(&dir).unwrap();
+        let classes = "force-app/main/default/classes";
+        write(
+            &dir,
+            &format!("{}/Fake.cls", classes),
+            "public class Fake {}",
+        );
+        write(
+            &dir,
+            &format!("{}/Fake.cls-meta.xml", classes),
+            "<ApexClass/>",
+        );
+        write(&dir, &format!("{}/Old.cls", classes), "public class Old {}");
+        write(
+            &dir,
+            &format!("{}/Old.cls-meta.xml", classes),
+            "<ApexClass/>",
+        );
+        write(
+            &dir,
+            "force-app/main/default/lwc/fake/fake.js",
+            "export default {}",
+        );
+        write(
+            &dir,
+            "force-app/main/default/lwc/fake/fake.html",
+            "<template/>",
+        );
+        write(&dir, "README.md", "readme");
+        commit_all(&repo, "initial");
+
+        write(
+            &dir,
+            &format!("{}/Fake.cls-meta.xml", classes),
+            "<ApexClass></ApexClass>",
+        );
+        fs::remove_file(dir.join(format!("{}/Old.cls", classes))).unwrap();
+        fs::remove_file(dir.join(format!("{}/Old.cls-meta.xml", classes))).unwrap();
+        fs::remove_file(dir.join("force-app/main/default/lwc/fake/fake.html")).unwrap();
+        write(&dir, "README.md", "changed");
+        commit_all(&repo, "change");
+
+        let delta = between(&repo, "HEAD~1", "HEAD", &["force-app"]).unwrap();
+        assert_eq!(
+            BTreeSet::from([
+                Component::new("ApexClass", "Fake"),
+                Component::new("LightningComponentBundle", "fake"),
+            ]),
+            delta.changed
+        );
+        assert_eq!(
+            BTreeSet::from([Component::new("ApexClass", "Old")]),
+            delta.deleted
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,412 @@
+use anyhow::{anyhow, Result};
+use cli_table::{print_stdout, Cell, Style, Table};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+
+use crate::cli::sf::{CliResult, PackageVersionInfo, SalesforceCli};
+use crate::project_config::PackageDependency;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    // the id is in packageAliases
+    Alias,
+    // the highest matching version on the Dev Hub
+    DevHub,
+    // a dependency of a dependency
+    Transitive,
+}
+
+const PACKAGE_VERSION_QUERY: &str = "SELECT Id, SubscriberPackageId, MajorVersion, MinorVersion, PatchVersion, BuildNumber, Dependencies FROM SubscriberPackageVersion WHERE Id = ";
+const PACKAGE_QUERY: &str = "SELECT Id, Name FROM SubscriberPackage WHERE Id = ";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedDependency {
+    pub name: String,
+    pub version_number: String,
+    pub version: String,
+    pub id: String,
+    // the packageAliases entry it was resolved from
+    pub alias: String,
+    pub source: Source,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransitiveDependency {
+    pub dependency: ResolvedDependency,
+    // the names of the package versions that depend on it
+    pub required_by: Vec<String>,
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Alias => write!(f, "packageAliases"),
+            Source::DevHub => write!(f, "Dev Hub"),
+            Source::Transitive => write!(f, "transitive"),
+        }
+    }
+}
+
+// finds the 04t id of every dependency, the Dev Hub is only asked when an alias does not have it
+pub fn resolve(
+    devhub: &str,
+    dependencies: &[PackageDependency],
+) -> Result<Vec<ResolvedDependency>> {
+    let package_ids = dependencies
+        .iter()
+        .filter(|x| !x.id.starts_with("04t"))
+        .filter_map(|x| x.package_id.clone())
+        .collect::<Vec<_>>();
+    let versions = if package_ids.is_empty() {
+        Vec::new()
+    } else {
+        let mut cli = SalesforceCli::new(Some(devhub.to_owned()));
+        match cli.list_package_versions(&package_ids, false)?.result {
+            Some(CliResult::PackageVersionList(x)) => x,
+            _ => Vec::new(),
+        }
+    };
+
+    let resolved = dependencies
+        .iter()
+        .map(|x| resolve_dependency(x, &versions))
+        .collect::<Result<Vec<_>>>()?;
+    if !resolved.is_empty() {
+        print_stdout(
+            resolved
+                .iter()
+                .map(|x| {
+                    vec![
+                        x.name.clone().cell(),
+                        x.version_number.clone().cell(),
+                        x.version.clone().cell(),
+                        x.id.clone().cell(),
+                        x.source.cell(),
+                    ]
+                })
+                .collect::<Vec<_>>()
+                .table()
+                .title(vec![
+                    "Dependency".cell().bold(true),
+                    "Version Number".cell().bold(true),
+                    "Resolved Version".cell().bold(true),
+                    "Id".cell().bold(true),
+                    "Source".cell().bold(true),
+                ])
+                .bold(true),
+        )?;
+    }
+    Ok(resolved)
+}
+
+// LATEST is the highest validated build, RELEASED the highest released one
+fn resolve_dependency(
+    dependency: &PackageDependency,
+    versions: &[PackageVersionInfo],
+) -> Result<ResolvedDependency> {
+    let version_number = dependency.version_number.clone().unwrap_or_default();
+    if dependency.id.starts_with("04t") {
+        return Ok(ResolvedDependency {
+            name: dependency.name.clone(),
+            version: version_number.clone(),
+            version_number,
+            id: dependency.id.clone(),
+            alias: dependency.alias.clone(),
+            source: Source::Alias,
+        });
+    }
+
+    let Some(package_id) = &dependency.package_id else {
+        return Err(anyhow!(
+            "dependency {} has no package id (0Ho) alias to resolve its version with",
+            dependency.name
+        ));
+    };
+    let Some((number, build)) = version_number.rsplit_once('.') else {
+        return Err(anyhow!(
+            "dependency {} needs a versionNumber like 1.0.0.LATEST to be resolved",
+            dependency.name
+        ));
+    };
+    let (kind, matches): (&str, fn(&PackageVersionInfo, &str) -> bool) = match build {
+        "LATEST" => ("validated", |x, _| x.is_released || !x.validation_skipped),
+        "RELEASED" => ("released", |x, _| x.is_released),
+        _ => ("", |x, build| get_build(&x.version) == build.parse().ok()),
+    };
+
+    versions
+        .iter()
+        .filter(|x| &x.package2_id == package_id)
+        .filter(|x| x.version.rsplit_once('.').map(|x| x.0) == Some(number))
+        .filter(|x| matches(x, build))
+        .max_by_key(|x| get_build(&x.version))
+        .map(|x| ResolvedDependency {
+            name: dependency.name.clone(),
+            version_number: version_number.clone(),
+            version: x.version.clone(),
+            id: x.subscriber_package_version_id.clone(),
+            alias: dependency.alias.clone(),
+            source: Source::DevHub,
+        })
+        .ok_or_else(|| {
+            anyhow!(
+                "no {}version of {} ({}) on the Dev Hub matches {}",
+                if kind.is_empty() {
+                    String::new()
+                } else {
+                    format!("{} ", kind)
+                },
+                dependency.name,
+                package_id,
+                version_number
+            )
+        })
+}
+
+// an alias pins a 04t whose versionNumber can be symbolic, e.g. 1.0.0.LATEST, so the real version
+// is read from the Dev Hub
+pub fn set_alias_versions(devhub: &str, resolved: &mut [ResolvedDependency]) -> Result<()> {
+    let mut cli = SalesforceCli::new(Some(devhub.to_owned()));
+    for dependency in resolved.iter_mut().filter(|x| x.source == Source::Alias) {
+        let record = query_one(&mut cli, PACKAGE_VERSION_QUERY, &dependency.id)?;
+        dependency.version = get_version(&record);
+    }
+    Ok(())
+}
+
+// the dependencies of the resolved package versions that are not resolved themselves, ordered so
+// every package comes after the packages it depends on
+pub fn find_transitive(
+    devhub: &str,
+    resolved: &[ResolvedDependency],
+) -> Result<Vec<TransitiveDependency>> {
+    let mut cli = SalesforceCli::new(Some(devhub.to_owned()));
+    let mut found: Vec<TransitiveDependency> = Vec::new();
+    // the dependency ids of every package version queried so far
+    let mut dependency_ids: HashMap<String, Vec<String>> = HashMap::new();
+    let mut parents = resolved
+        .iter()
+        .map(|x| (x.name.clone(), x.id.clone()))
+        .collect::<Vec<_>>();
+
+    while !parents.is_empty() {
+        let mut next = Vec::new();
+        for (parent, parent_id) in &parents {
+            let record = query_one(&mut cli, PACKAGE_VERSION_QUERY, parent_id)?;
+            let ids = get_dependency_ids(&record);
+            for id in &ids {
+                if resolved.iter().any(|x| &x.id == id) {
+                    continue;
+                }
+                if let Some(known) = found.iter_mut().find(|x| &x.dependency.id == id) {
+                    known.required_by.push(parent.clone());
+                    continue;
+                }
+
+                let version = query_one(&mut cli, PACKAGE_VERSION_QUERY, id)?;
+                let package_id = version["SubscriberPackageId"].as_str().unwrap_or_default();
+                let package = query_one(&mut cli, PACKAGE_QUERY, package_id)?;
+                let name = package["Name"].as_str().unwrap_or(package_id).to_owned();
+                next.push((name.clone(), id.clone()));
+                found.push(TransitiveDependency {
+                    dependency: ResolvedDependency {
+                        name,
+                        version_number: String::new(),
+                        version: get_version(&version),
+                        id: id.clone(),
+                        alias: String::new(),
+                        source: Source::Transitive,
+                    },
+                    required_by: vec![parent.clone()],
+                });
+            }
+            dependency_ids.insert(parent_id.clone(), ids);
+        }
+        parents = next;
+    }
+    Ok(sort_for_install(found, &dependency_ids))
+}
+
+// a package found at a shallow level can also be needed by a deeper one, so the install order
+// follows the dependencies rather than the level a package was found at
+fn sort_for_install(
+    mut found: Vec<TransitiveDependency>,
+    dependency_ids: &HashMap<String, Vec<String>>,
+) -> Vec<TransitiveDependency> {
+    let mut order = Vec::new();
+    for dependency in &found {
+        visit(&dependency.dependency.id, dependency_ids, &mut order);
+    }
+    order
+        .iter()
+        .filter_map(|id| {
+            let index = found.iter().position(|x| &x.dependency.id == id)?;
+            Some(found.remove(index))
+        })
+        .collect()
+}
+
+fn visit(id: &str, dependency_ids: &HashMap<String, Vec<String>>, order: &mut Vec<String>) {
+    if order.iter().any(|x| x == id) {
+        return;
+    }
+    for dependency_id in dependency_ids.get(id).into_iter().flatten() {
+        visit(dependency_id, dependency_ids, order);
+    }
+    order.push(id.to_owned());
+}
+
+fn query_one(cli: &mut SalesforceCli, query: &str, id: &str) -> Result<Value> {
+    match cli.query(&format!("{}'{}'", query, id), true)?.result {
+        Some(CliResult::Query { mut records, .. }) if !records.is_empty() => {
+            Ok(records.swap_remove(0))
+        }
+        _ => Err(anyhow!("could not find {} on the Dev Hub", id)),
+    }
+}
+
+// Dependencies is null for package versions without dependencies
+fn get_dependency_ids(record: &Value) -> Vec<String> {
+    record["Dependencies"]["ids"]
+        .as_array()
+        .map(|ids| {
+            ids.iter()
+                .filter_map(|x| x["subscriberPackageVersionId"].as_str())
+                .map(|x| x.to_owned())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn get_version(record: &Value) -> String {
+    [
+        "MajorVersion",
+        "MinorVersion",
+        "PatchVersion",
+        "BuildNumber",
+    ]
+    .iter()
+    .map(|x| record[x].as_u64().unwrap_or_default().to_string())
+    .collect::<Vec<_>>()
+    .join(".")
+}
+
+fn get_build(version: &str) -> Option<u32> {
+    version.rsplit_once('.')?.1.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project_config;
+
+    fn version(version: &str, id: &str, is_released: bool) -> PackageVersionInfo {
+        PackageVersionInfo {
+            package2_id: String::from("0HoB00000004CFuKAM"),
+            package2_name: String::from("Expense Manager"),
+            version: version.to_owned(),
+            subscriber_package_version_id: id.to_owned(),
+            is_released,
+            validation_skipped: false,
+        }
+    }
+
+    #[test]
+    fn it_should_read_the_dependencies_of_a_package_version() {
+        let record: Value = serde_json::from_str(
+            r#"{
+  "Id": "04tB0000000000REL1",
+  "SubscriberPackageId": "033B00000000000AAA",
+  "MajorVersion": 3,
+  "MinorVersion": 1,
+  "PatchVersion": 0,
+  "BuildNumber": 2,
+  "Dependencies": { "ids": [{ "subscriberPackageVersionId": "04tB0000000000DEP1" }] }
+}"#,
+        )
+        .unwrap();
+        assert_eq!(vec!["04tB0000000000DEP1"], get_dependency_ids(&record));
+        assert_eq!("3.1.0.2", get_version(&record));
+        assert!(get_dependency_ids(&serde_json::json!({ "Dependencies": null })).is_empty());
+    }
+
+    fn transitive(id: &str) -> TransitiveDependency {
+        TransitiveDependency {
+            dependency: ResolvedDependency {
+                name: id.to_owned(),
+                version_number: String::new(),
+                version: String::from("1.0.0.1"),
+                id: id.to_owned(),
+                alias: String::new(),
+                source: Source::Transitive,
+            },
+            required_by: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn it_should_install_a_dependency_before_every_package_that_needs_it() {
+        // A -> Z, B -> X and Z -> X, X and Z are both found at the first level
+        let dependency_ids = HashMap::from([
+            (String::from("A"), vec![String::from("Z")]),
+            (String::from("B"), vec![String::from("X")]),
+            (String::from("Z"), vec![String::from("X")]),
+            (String::from("X"), Vec::new()),
+        ]);
+        let sorted = sort_for_install(vec![transitive("Z"), transitive("X")], &dependency_ids);
+        assert_eq!(
+            vec!["X", "Z"],
+            sorted
+                .iter()
+                .map(|x| x.dependency.id.as_str())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn it_should_resolve_symbolic_versions() {
+        let project_config =
+            project_config::read(Some(String::from("tests/resources/sfdx-project-deps.json")))
+                .unwrap();
+        let dependencies = project_config.get_packages()[0]
+            .dependencies
+            .as_ref()
+            .unwrap();
+        let mut versions = vec![
+            version("3.1.0.1", "04tB0000000000REL1", true),
+            version("3.1.0.2", "04tB0000000000REL2", true),
+            version("3.1.0.3", "04tB0000000000BETA", false),
+            version("3.2.0.1", "04tB0000000000NEWR", true),
+        ];
+        versions.push(PackageVersionInfo {
+            validation_skipped: true,
+            ..version("3.1.0.4", "04tB0000000000SKIP", false)
+        });
+
+        let resolved = dependencies
+            .iter()
+            .map(|x| resolve_dependency(x, &versions).map(|x| (x.id, x.source)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            (String::from("04tB0000000000BETA"), Source::DevHub),
+            *resolved[0].as_ref().unwrap()
+        );
+        assert_eq!(
+            (String::from("04tB0000000000REL2"), Source::DevHub),
+            *resolved[1].as_ref().unwrap()
+        );
+        assert_eq!(
+            (String::from("04tB0000000000REL1"), Source::DevHub),
+            *resolved[2].as_ref().unwrap()
+        );
+        assert_eq!(
+            (String::from("04tB00000000000001"), Source::Alias),
+            *resolved[3].as_ref().unwrap()
+        );
+        assert_eq!(
+            "no released version of Expense Manager (0HoB00000004CFuKAM) on the Dev Hub matches 4.0.0.RELEASED",
+            resolved[4].as_ref().unwrap_err().to_string()
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use std::{
+    collections::BTreeSet,
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
+};
+use text_io::read;
+
+use crate::cli::sf::SalesforceCli;
+use crate::metadata::{Component, Manifest};
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Phase {
+    // delete the components before the rest of the deployment
+    Pre,
+    // delete the components after the rest of the deployment
+    Post,
+}
+
+#[derive(Debug)]
+pub struct DestructiveChanges {
+    pub components: BTreeSet<Component>,
+    pub phase: Phase,
+}
+
+impl DestructiveChanges {
+    pub fn new(components: BTreeSet<Component>, phase: Phase) -> DestructiveChanges {
+        DestructiveChanges { components, phase }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+
+    pub fn get_file_name(&self) -> &str {
+        match self.phase {
+            Phase::Pre => "destructiveChangesPre.xml",
+            Phase::Post => "destructiveChangesPost.xml",
+        }
+    }
+
+    pub fn write(&self, dir: &Path, api_version: &str) -> Result<PathBuf> {
+        let mut manifest = Manifest::new(api_version);
+        self.components.iter().for_each(|x| manifest.add(x));
+        let path = dir.join(self.get_file_name());
+        manifest.write(&path)?;
+        Ok(path)
+    }
+
+    // deleting metadata from production needs an explicit opt in or a confirmation
+    pub fn check_allowed(
+        &self,
+        cli: &mut SalesforceCli,
+        target_org: &str,
+        allow_destructive: bool,
+    ) -> Result<()> {
+        if self.is_empty() || allow_destructive || !cli.is_production()? {
+            return Ok(());
+        }
+
+        println!(
+            "The following components will be deleted from {}:",
+            target_org
+        );
+        for component in &self.components {
+            println!("  {} {}", component.component_type, component.member);
+        }
+        if !io::stdin().is_terminal() {
+            return Err(anyhow!(
+                "{} is a production org, pass --allow-destructive to delete metadata from it",
+                target_org
+            ));
+        }
+
+        print!("{} is a production org, continue? [y/N] ", target_org);
+        io::stdout().flush()?;
+        let answer: String = read!("{}\n");
+        if answer.trim().eq_ignore_ascii_case("y") {
+            Ok(())
+        } else {
+            Err(anyhow!("deployment cancelled"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn it_should_write_destructive_changes_for_the_phase() {
+        let dir = std::env::temp_dir().join(format!("ht-destructive-{}", rand::random::<u64>()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let destructive_changes = DestructiveChanges::new(
+            BTreeSet::from([Component::new("ApexClass", "Old")]),
+            Phase::Pre,
+        );
+        let path = destructive_changes.write(&dir, "61.0").unwrap();
+        assert_eq!(dir.join("destructiveChangesPre.xml"), path);
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .contains("<members>Old</members>"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
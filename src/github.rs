@@ -0,0 +1,210 @@
+use anyhow::Result;
+use std::{env, fs, io::Write};
+
+use crate::cli::sf::SfCliResult;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub title: Option<String>,
+    pub message: String,
+}
+
+impl Annotation {
+    pub fn new(message: &str) -> Annotation {
+        Annotation {
+            file: None,
+            line: None,
+            column: None,
+            title: None,
+            message: message.to_owned(),
+        }
+    }
+
+    fn to_workflow_command(&self) -> String {
+        let mut properties = Vec::new();
+        if let Some(file) = &self.file {
+            properties.push(format!("file={}", escape_property(file)));
+        }
+        if let Some(line) = self.line {
+            properties.push(format!("line={}", line));
+        }
+        if let Some(column) = self.column {
+            properties.push(format!("col={}", column));
+        }
+        if let Some(title) = &self.title {
+            properties.push(format!("title={}", escape_property(title)));
+        }
+
+        if properties.is_empty() {
+            format!("::error::{}", escape_data(&self.message))
+        } else {
+            format!(
+                "::error {}::{}",
+                properties.join(","),
+                escape_data(&self.message)
+            )
+        }
+    }
+}
+
+pub fn is_enabled() -> bool {
+    env::var("GITHUB_ACTIONS").is_ok_and(|x| x == "true")
+}
+
+pub fn annotate(annotations: &[Annotation]) {
+    if !is_enabled() {
+        return;
+    }
+    for annotation in annotations {
+        println!("{}", annotation.to_workflow_command());
+    }
+}
+
+// hides a secret like a login url in the rest of the job log
+pub fn mask(value: &str) {
+    if is_enabled() {
+        println!("{}", format_mask(value));
+    }
+}
+
+pub fn report(result: &impl SfCliResult) -> Result<()> {
+    if !is_enabled() {
+        return Ok(());
+    }
+    annotate(&result.get_annotations());
+    append_step_summary(&result.get_markdown_summary())
+}
+
+pub fn append_step_summary(markdown: &str) -> Result<()> {
+    if !is_enabled() || markdown.is_empty() {
+        return Ok(());
+    }
+    match env::var("GITHUB_STEP_SUMMARY") {
+        Ok(path) => append_to_file(&path, &format!("{}\n", markdown)),
+        Err(_) => Ok(()),
+    }
+}
+
+pub fn set_output(name: &str, value: &str) -> Result<()> {
+    if !is_enabled() {
+        return Ok(());
+    }
+    match env::var("GITHUB_OUTPUT") {
+        Ok(path) => append_to_file(&path, &format_output(name, value)),
+        Err(_) => Ok(()),
+    }
+}
+
+pub fn markdown_table(headers: &[&str], rows: Vec<Vec<String>>) -> String {
+    let mut table = format!(
+        "| {} |\n|{}\n",
+        headers.join(" | "),
+        " --- |".repeat(headers.len())
+    );
+    for row in rows {
+        let cells = row
+            .iter()
+            .map(|x| x.trim().replace('|', "\\|").replace('\n', "<br>"))
+            .collect::<Vec<_>>();
+        table.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+    table
+}
+
+fn format_output(name: &str, value: &str) -> String {
+    if value.contains('\n') {
+        let delimiter = format!("ht_{}", rand::random::<u64>());
+        format!("{name}<<{delimiter}\n{value}\n{delimiter}\n")
+    } else {
+        format!("{}={}\n", name, value)
+    }
+}
+
+fn format_mask(value: &str) -> String {
+    format!("::add-mask::{}", escape_data(value))
+}
+
+fn append_to_file(path: &str, contents: &str) -> Result<()> {
+    let mut f = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    f.write_all(contents.as_bytes())?;
+    f.flush()?;
+    Ok(())
+}
+
+fn escape_data(value: &str) -> String {
+    value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_property(value: &str) -> String {
+    escape_data(value).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_format_an_annotation_with_a_location() {
+        let annotation = Annotation {
+            file: Some(String::from("force-app/main/default/classes/Test.cls")),
+            line: Some(10),
+            column: Some(1),
+            title: Some(String::from("ApexClass Test")),
+            message: String::from("Variable does not exist: foo"),
+        };
+        assert_eq!(
+            "::error file=force-app/main/default/classes/Test.cls,line=10,col=1,title=ApexClass Test::Variable does not exist: foo",
+            annotation.to_workflow_command()
+        );
+    }
+
+    #[test]
+    fn it_should_escape_annotation_messages() {
+        let annotation = Annotation::new("100% failed\nsee logs");
+        assert_eq!(
+            "::error::100%25 failed%0Asee logs",
+            annotation.to_workflow_command()
+        );
+    }
+
+    #[test]
+    fn it_should_mask_a_value() {
+        assert_eq!(
+            "::add-mask::https://example.my.salesforce.com/secur/frontdoor.jsp?sid=00D%25abc",
+            format_mask("https://example.my.salesforce.com/secur/frontdoor.jsp?sid=00D%abc")
+        );
+    }
+
+    #[test]
+    fn it_should_build_a_markdown_table() {
+        let table = markdown_table(
+            &["Test", "Message"],
+            vec![vec![
+                String::from("TestClass.runTest"),
+                String::from("Expected: 2 | Actual: 3\nline 10"),
+            ]],
+        );
+        assert_eq!(
+            "| Test | Message |\n| --- | --- |\n| TestClass.runTest | Expected: 2 \\| Actual: 3<br>line 10 |\n",
+            table
+        );
+    }
+
+    #[test]
+    fn it_should_format_multiline_outputs_with_a_delimiter() {
+        assert_eq!("version=1.0.0\n", format_output("version", "1.0.0"));
+
+        let output = format_output("summary", "a\nb");
+        let delimiter = output.lines().next().unwrap().replace("summary<<", "");
+        assert_eq!(format!("summary<<{0}\na\nb\n{0}\n", delimiter), output);
+    }
+}
@@ -0,0 +1,290 @@
+use serde_json::{Map, Value};
+use std::{
+    fmt::{self, Display, Formatter},
+    path::Path,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Finding {
+    pub severity: Severity,
+    // JSON path of the offending value, e.g. $.packageDirectories[0].versionNumber
+    pub path: String,
+    pub message: String,
+    pub hint: String,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}: {} ({})",
+            self.severity, self.path, self.message, self.hint
+        )
+    }
+}
+
+impl Finding {
+    // dependency problems only fail `ht lint-project`, loading the project warns about them so
+    // projects with e.g. a "1.0" dependency version keep working
+    pub fn blocks_loading(&self) -> bool {
+        self.severity == Severity::Error && !self.path.contains(".dependencies[")
+    }
+
+    fn new(severity: Severity, path: String, message: &str, hint: &str) -> Finding {
+        Finding {
+            severity,
+            path,
+            message: message.to_owned(),
+            hint: hint.to_owned(),
+        }
+    }
+}
+
+// checks an sfdx-project.json document, paths on disk are only checked against a project root
+pub fn lint(document: &Value, root: Option<&Path>) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let Some(project) = document.as_object() else {
+        findings.push(Finding::new(
+            Severity::Error,
+            String::from("$"),
+            "sfdx-project.json is not a JSON object",
+            "wrap the project properties in { }",
+        ));
+        return findings;
+    };
+
+    let empty = Map::new();
+    let aliases = project
+        .get("packageAliases")
+        .and_then(Value::as_object)
+        .unwrap_or(&empty);
+
+    let directories = match project.get("packageDirectories").and_then(Value::as_array) {
+        Some(x) => x,
+        None => {
+            findings.push(Finding::new(
+                Severity::Error,
+                String::from("$.packageDirectories"),
+                "packageDirectories is missing",
+                "add at least one package directory with a path",
+            ));
+            return findings;
+        }
+    };
+
+    let defaults = directories
+        .iter()
+        .filter(|x| x["default"] == Value::Bool(true))
+        .count();
+    if defaults > 1 {
+        findings.push(Finding::new(
+            Severity::Error,
+            String::from("$.packageDirectories"),
+            &format!("{} package directories are marked as default", defaults),
+            "set \"default\": true on exactly one package directory",
+        ));
+    }
+
+    for (index, directory) in directories.iter().enumerate() {
+        let path = format!("$.packageDirectories[{}]", index);
+        lint_package_directory(directory, &path, aliases, root, &mut findings);
+    }
+    findings
+}
+
+fn lint_package_directory(
+    directory: &Value,
+    path: &str,
+    aliases: &Map<String, Value>,
+    root: Option<&Path>,
+    findings: &mut Vec<Finding>,
+) {
+    if let (Some(root), Some(directory_path)) = (root, directory["path"].as_str()) {
+        if !root.join(directory_path).exists() {
+            findings.push(Finding::new(
+                Severity::Error,
+                format!("{}.path", path),
+                &format!("{} does not exist", directory_path),
+                "create the directory or fix the path",
+            ));
+        }
+    }
+    if let (Some(root), Some(definition_file)) = (root, directory["definitionFile"].as_str()) {
+        if !root.join(definition_file).exists() {
+            findings.push(Finding::new(
+                Severity::Warning,
+                format!("{}.definitionFile", path),
+                &format!("{} does not exist", definition_file),
+                "create the scratch org definition or fix the path",
+            ));
+        }
+    }
+
+    let Some(package) = directory["package"].as_str() else {
+        return;
+    };
+    match directory["versionNumber"].as_str() {
+        None => findings.push(Finding::new(
+            Severity::Error,
+            format!("{}.versionNumber", path),
+            &format!("package {} has no versionNumber", package),
+            "add a versionNumber like \"1.0.0.NEXT\"",
+        )),
+        Some(version) if !is_version(version, &["NEXT"]) => findings.push(Finding::new(
+            Severity::Error,
+            format!("{}.versionNumber", path),
+            &format!("{} is not a valid version number", version),
+            "use major.minor.patch.build where build is a number or NEXT",
+        )),
+        _ => {}
+    }
+    if !aliases
+        .get(package)
+        .and_then(Value::as_str)
+        .is_some_and(|x| x.starts_with("0Ho"))
+    {
+        findings.push(Finding::new(
+            Severity::Warning,
+            format!("$.packageAliases[\"{}\"]", package),
+            &format!("package {} has no 0Ho package id alias", package),
+            "run `sf package create` or add the package id to packageAliases",
+        ));
+    }
+
+    let Some(dependencies) = directory["dependencies"].as_array() else {
+        return;
+    };
+    for (index, dependency) in dependencies.iter().enumerate() {
+        let path = format!("{}.dependencies[{}]", path, index);
+        let Some(name) = dependency["package"].as_str() else {
+            findings.push(Finding::new(
+                Severity::Error,
+                format!("{}.package", path),
+                "dependency has no package",
+                "add the package name or alias of the dependency",
+            ));
+            continue;
+        };
+        let version = dependency["versionNumber"].as_str();
+        if let Some(version) = version {
+            if !is_version(version, &["LATEST", "RELEASED"]) {
+                findings.push(Finding::new(
+                    Severity::Error,
+                    format!("{}.versionNumber", path),
+                    &format!("{} is not a valid version number", version),
+                    "use major.minor.patch.build where build is a number, LATEST or RELEASED",
+                ));
+            }
+        }
+        let is_aliased = aliases.contains_key(name)
+            || version.is_some_and(|x| aliases.contains_key(&format!("{}@{}", name, x)));
+        if !is_aliased {
+            findings.push(Finding::new(
+                Severity::Error,
+                path,
+                &format!(
+                    "dependency {} of package {} is not in packageAliases",
+                    name, package
+                ),
+                "add the package or package version id of the dependency to packageAliases",
+            ));
+        }
+    }
+}
+
+fn is_version(version: &str, keywords: &[&str]) -> bool {
+    let parts = version.split('.').collect::<Vec<_>>();
+    parts.len() == 4
+        && parts[..3]
+            .iter()
+            .all(|x| !x.is_empty() && x.chars().all(|x| x.is_ascii_digit()))
+        && (keywords.contains(&parts[3])
+            || (!parts[3].is_empty() && parts[3].chars().all(|x| x.is_ascii_digit())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn it_should_find_problems_in_project_json() {
+        let document: Value = serde_json::from_str(
+            &fs::read_to_string("tests/resources/sfdx-project-lint.json").unwrap(),
+        )
+        .unwrap();
+        let findings = lint(
+            &document,
+            Some(Path::new("tests/resources/fake-salesforce-project")),
+        );
+        let found = findings
+            .iter()
+            .map(|x| (x.severity, x.path.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (Severity::Error, "$.packageDirectories"),
+                (Severity::Warning, "$.packageDirectories[0].definitionFile"),
+                (Severity::Error, "$.packageDirectories[0].versionNumber"),
+                (Severity::Error, "$.packageDirectories[1].path"),
+                (Severity::Error, "$.packageDirectories[1].versionNumber"),
+                (Severity::Warning, "$.packageAliases[\"Other\"]"),
+                (Severity::Error, "$.packageDirectories[1].dependencies[0]"),
+                (
+                    Severity::Error,
+                    "$.packageDirectories[1].dependencies[1].versionNumber"
+                ),
+            ],
+            found
+        );
+    }
+
+    #[test]
+    fn it_should_require_full_dependency_version_numbers() {
+        let document: Value =
+            serde_json::from_str(&fs::read_to_string("tests/resources/sfdx-project.json").unwrap())
+                .unwrap();
+        let errors = lint(&document, None)
+            .into_iter()
+            .filter(|x| x.severity == Severity::Error)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec!["$.packageDirectories[1].dependencies[0].versionNumber"],
+            errors.iter().map(|x| x.path.as_str()).collect::<Vec<_>>()
+        );
+        // the project still loads, only `ht lint-project` fails on it
+        assert!(!errors[0].blocks_loading());
+    }
+
+    #[test]
+    fn it_should_accept_a_valid_project_json() {
+        let document: Value = serde_json::from_str(
+            &fs::read_to_string("tests/resources/fake-salesforce-project/sfdx-project.json")
+                .unwrap(),
+        )
+        .unwrap();
+        let findings = lint(
+            &document,
+            Some(Path::new("tests/resources/fake-salesforce-project")),
+        );
+        assert!(findings.iter().all(|x| x.severity == Severity::Warning));
+        assert!(is_version("1.0.0.LATEST", &["LATEST"]));
+        assert!(!is_version("1.0", &["LATEST"]));
+        assert!(!is_version("1.0.x.1", &[]));
+    }
+}
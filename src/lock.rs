@@ -0,0 +1,216 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+use crate::dependencies::{self, ResolvedDependency};
+use crate::project_config::PackageDependency;
+
+pub const LOCK_PATH: &str = "ht.lock";
+const LOCK_VERSION: u32 = 1;
+const HEADER: &str = "# Written by `ht lock`, do not edit.\n\n";
+
+// the package versions the dependencies resolved to, installed in this order
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Lockfile {
+    version: u32,
+    #[serde(default, rename = "dependency")]
+    pub dependencies: Vec<LockedDependency>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct LockedDependency {
+    pub name: String,
+    // as written in sfdx-project.json, transitive dependencies have none
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub version_number: String,
+    pub version: String,
+    pub id: String,
+    // the packageAliases entry it was resolved from
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub alias: String,
+    // the dependencies that need a transitive dependency
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required_by: Vec<String>,
+}
+
+impl From<&ResolvedDependency> for LockedDependency {
+    fn from(dependency: &ResolvedDependency) -> Self {
+        LockedDependency {
+            name: dependency.name.clone(),
+            version_number: dependency.version_number.clone(),
+            version: dependency.version.clone(),
+            id: dependency.id.clone(),
+            alias: dependency.alias.clone(),
+            required_by: Vec::new(),
+        }
+    }
+}
+
+impl Lockfile {
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let content = toml::to_string(self)?;
+        fs::write(path, format!("{}{}", HEADER, content))?;
+        Ok(())
+    }
+
+    // what changed in sfdx-project.json since the lockfile was written
+    pub fn get_drift(&self, dependencies: &[PackageDependency]) -> Vec<String> {
+        let mut drift = Vec::new();
+        let direct = self
+            .dependencies
+            .iter()
+            .filter(|x| x.required_by.is_empty())
+            .collect::<Vec<_>>();
+
+        for dependency in dependencies {
+            let version_number = dependency.version_number.clone().unwrap_or_default();
+            let Some(locked) = direct
+                .iter()
+                .find(|x| x.name == dependency.name && x.version_number == version_number)
+            else {
+                drift.push(format!(
+                    "{} is not locked",
+                    format!("{} {}", dependency.name, version_number).trim_end()
+                ));
+                continue;
+            };
+            if locked.alias != dependency.alias {
+                drift.push(format!(
+                    "{} is locked from alias {} but sfdx-project.json uses {}",
+                    dependency.name, locked.alias, dependency.alias
+                ));
+            } else if dependency.id.starts_with("04t") && dependency.id != locked.id {
+                drift.push(format!(
+                    "{} is locked to {} but {} is {}",
+                    dependency.name, locked.id, dependency.alias, dependency.id
+                ));
+            }
+        }
+        for locked in direct {
+            if !dependencies.iter().any(|x| x.name == locked.name) {
+                drift.push(format!(
+                    "{} is locked but not a dependency anymore",
+                    locked.name
+                ));
+            }
+        }
+        drift
+    }
+
+    pub fn check(&self, dependencies: &[PackageDependency]) -> Result<()> {
+        let drift = self.get_drift(dependencies);
+        if drift.is_empty() {
+            return Ok(());
+        }
+        Err(anyhow!(
+            "{} is out of date with sfdx-project.json, run `ht lock`: {}",
+            LOCK_PATH,
+            drift.join(", ")
+        ))
+    }
+}
+
+pub fn read(path: &Path) -> Result<Option<Lockfile>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let lockfile: Lockfile = toml::from_str(&fs::read_to_string(path)?)
+        .map_err(|e| anyhow!("{} is not in expected format: {}", path.display(), e))?;
+    if lockfile.version != LOCK_VERSION {
+        return Err(anyhow!(
+            "{} has version {}, this ht reads version {}",
+            path.display(),
+            lockfile.version,
+            LOCK_VERSION
+        ));
+    }
+    Ok(Some(lockfile))
+}
+
+// resolves the dependencies and their dependencies on the Dev Hub
+pub fn create(devhub: &str, dependencies: &[PackageDependency]) -> Result<Lockfile> {
+    let mut resolved = dependencies::resolve(devhub, dependencies)?;
+    dependencies::set_alias_versions(devhub, &mut resolved)?;
+    let transitive = dependencies::find_transitive(devhub, &resolved)?;
+
+    let mut locked = transitive
+        .iter()
+        .map(|x| LockedDependency {
+            required_by: x.required_by.clone(),
+            ..LockedDependency::from(&x.dependency)
+        })
+        .collect::<Vec<_>>();
+    locked.extend(resolved.iter().map(LockedDependency::from));
+    Ok(Lockfile {
+        version: LOCK_VERSION,
+        dependencies: locked,
+    })
+}
+
+// the package version ids verify installs, from the lockfile when there is one
+pub fn get_install_ids(
+    devhub: &str,
+    dependencies: &[PackageDependency],
+    update: bool,
+) -> Result<Vec<String>> {
+    let path = Path::new(LOCK_PATH);
+    let lockfile = match read(path)? {
+        Some(lockfile) if !update => {
+            lockfile.check(dependencies)?;
+            lockfile
+        }
+        Some(_) => {
+            let lockfile = create(devhub, dependencies)?;
+            lockfile.write(path)?;
+            lockfile
+        }
+        None => {
+            return Ok(dependencies::resolve(devhub, dependencies)?
+                .into_iter()
+                .map(|x| x.id)
+                .collect())
+        }
+    };
+    Ok(lockfile.dependencies.into_iter().map(|x| x.id).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project_config;
+
+    #[test]
+    fn it_should_find_drift_between_the_project_and_the_lockfile() {
+        let mut project_config = project_config::read(Some(String::from(
+            "tests/resources/sfdx-project-valid.json",
+        )))
+        .unwrap();
+        let dependencies = project_config.get_dependencies().unwrap();
+        let lockfile = read(Path::new("tests/resources/ht.lock")).unwrap().unwrap();
+        assert_eq!(3, lockfile.dependencies.len());
+        assert_eq!(vec!["B"], lockfile.dependencies[0].required_by);
+        assert!(lockfile.get_drift(&dependencies).is_empty());
+
+        let mut changed = lockfile.dependencies.clone();
+        changed[1].id = String::from("04tB0000000000OTHR");
+        changed[2].version_number = String::from("2.1");
+        let changed = Lockfile {
+            version: LOCK_VERSION,
+            dependencies: changed,
+        };
+        assert_eq!(
+            vec![
+                "A is locked to 04tB0000000000OTHR but A is 04tB00000000000000",
+                "B 2.0 is not locked",
+            ],
+            changed.get_drift(&dependencies)
+        );
+
+        let path = std::env::temp_dir().join(format!("ht-{}.lock", rand::random::<u64>()));
+        lockfile.write(&path).unwrap();
+        assert_eq!(lockfile, read(&path).unwrap().unwrap());
+        fs::remove_file(path).unwrap();
+    }
+}
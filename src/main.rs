@@ -2,17 +2,48 @@ use anyhow::anyhow;
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 use cli::sf;
+use env_logger::Env;
+use log::warn;
 use rand::Rng;
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
 
+mod approval;
 mod cli;
 mod commands;
+mod config;
+mod delta;
+mod dependencies;
+mod destructive;
+mod github;
+mod lint;
+mod lock;
+mod metadata;
+mod permissions;
 mod project;
 mod project_config;
+mod state;
 mod system;
+mod template;
+mod users;
 
 #[derive(Parser)]
 #[clap(name = "HT", about = "Salesforce Build Tool")]
 struct Cli {
+    #[arg(
+        long = "project-dir",
+        global = true,
+        help = "The directory with sfdx-project.json, defaults to the closest one from the current directory"
+    )]
+    project_dir: Option<PathBuf>,
+    #[arg(
+        long = "env",
+        global = true,
+        help = "The environment from ht.toml to use the settings of, defaults to HT_ENV"
+    )]
+    env: Option<String>,
     #[command(subcommand)]
     command: Commands,
 }
@@ -24,6 +55,49 @@ enum Commands {
         devhub: Option<String>,
         #[arg(short = 'o', long = "target-out")]
         target_org: Option<String>,
+        #[arg(
+            long = "definition-file",
+            help = "Scratch org definition, defaults to the definitionFile of the packages"
+        )]
+        definition_file: Option<String>,
+        #[arg(long = "duration-days", value_parser = clap::value_parser!(u32).range(1..=30))]
+        duration_days: Option<u32>,
+        #[arg(
+            long = "edition",
+            value_parser = [
+                "developer",
+                "enterprise",
+                "group",
+                "professional",
+                "partner-developer",
+                "partner-enterprise",
+                "partner-group",
+                "partner-professional",
+            ]
+        )]
+        edition: Option<String>,
+        #[arg(
+            long = "no-namespace",
+            help = "Create the scratch org without the namespace"
+        )]
+        no_namespace: bool,
+        #[arg(long = "keep", help = "Keep the scratch org instead of deleting it")]
+        keep: bool,
+        #[arg(
+            long = "keep-on-failure",
+            help = "Keep the scratch org when verify fails"
+        )]
+        keep_on_failure: bool,
+        #[arg(
+            long = "approve",
+            help = "Approve changing the org of an environment that requires approval"
+        )]
+        approve: bool,
+        #[arg(
+            long = "update",
+            help = "Resolve the dependencies again instead of installing the ones in ht.lock"
+        )]
+        update: bool,
     },
     Version {
         #[arg(long = "dry-run")]
@@ -33,46 +107,369 @@ enum Commands {
         #[arg(long = "push", help = "Git push after committing")]
         push: bool,
     },
-    #[command(about = "Releases the package")]
+    #[command(about = "Releases the package, not supported yet")]
     Release {},
+    #[command(about = "Validates a deployment of the project without saving it")]
+    Validate {
+        #[arg(short = 'o', long = "target-org")]
+        target_org: Option<String>,
+        #[arg(short = 'l', long = "test-level")]
+        test_level: Option<String>,
+        #[arg(
+            long = "approve",
+            help = "Approve changing the org of an environment that requires approval"
+        )]
+        approve: bool,
+    },
+    #[command(about = "Deploys the project to an org")]
+    Deploy {
+        #[arg(short = 'o', long = "target-org")]
+        target_org: Option<String>,
+        #[arg(
+            short = 'l',
+            long = "test-level",
+            help = "The tests to run, defaults to the test-level setting or the org's default"
+        )]
+        test_level: Option<String>,
+        #[arg(
+            long = "quick",
+            num_args = 0..=1,
+            help = "Quick deploy a validated deployment, defaults to the last validation of the target org"
+        )]
+        quick: Option<Option<String>>,
+        #[arg(
+            long = "since",
+            conflicts_with = "quick",
+            help = "Only deploy components that changed since this git ref"
+        )]
+        since: Option<String>,
+        #[arg(
+            long = "until",
+            requires = "since",
+            default_value = "HEAD",
+            help = "The git ref to compare against --since"
+        )]
+        until: String,
+        #[arg(
+            long = "destructive-phase",
+            requires = "since",
+            value_enum,
+            default_value_t = destructive::Phase::Post,
+            help = "Delete removed components before or after deploying the changed ones"
+        )]
+        destructive_phase: destructive::Phase,
+        #[arg(
+            long = "allow-destructive",
+            help = "Delete removed components from production orgs without asking"
+        )]
+        allow_destructive: bool,
+        #[arg(
+            long = "approve",
+            help = "Approve changing the org of an environment that requires approval"
+        )]
+        approve: bool,
+    },
+    #[command(about = "Checks sfdx-project.json for problems")]
+    LintProject {},
+    #[command(about = "Manages scratch orgs")]
+    Org {
+        #[command(subcommand)]
+        command: OrgCommands,
+    },
+    #[command(about = "Manages the packageAliases of sfdx-project.json")]
+    Alias {
+        #[command(subcommand)]
+        command: AliasCommands,
+    },
+    #[command(about = "Shows how the packages depend on each other")]
+    Deps {
+        #[command(subcommand)]
+        command: DepsCommands,
+    },
+    #[command(about = "Resolves the dependencies and writes them to ht.lock")]
+    Lock {
+        #[arg(short = 'v', long = "devhub")]
+        devhub: Option<String>,
+        #[arg(
+            long = "check",
+            help = "Fail when ht.lock does not match the dependencies in sfdx-project.json"
+        )]
+        check: bool,
+    },
+    #[command(about = "Shows the ht settings")]
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum OrgCommands {
+    #[command(about = "Deletes the scratch orgs ht left behind on the Dev Hub")]
+    Prune {
+        #[arg(short = 'v', long = "devhub")]
+        devhub: Option<String>,
+        #[arg(
+            long = "max-age-hours",
+            default_value_t = 24,
+            help = "Only delete scratch orgs created at least this many hours ago"
+        )]
+        max_age_hours: u64,
+        #[arg(long = "dry-run", help = "List the scratch orgs without deleting them")]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum AliasCommands {
+    #[command(about = "Lists the package aliases and whether the project uses them")]
+    List {},
+    #[command(about = "Adds or changes a package alias")]
+    Add {
+        alias: String,
+        #[arg(help = "The package (0Ho) or package version (04t) id")]
+        id: String,
+    },
+    #[command(about = "Removes a package alias")]
+    Remove { alias: String },
+    #[command(
+        about = "Adds the released versions of the project's packages from the Dev Hub and removes unused aliases"
+    )]
+    Sync {
+        #[arg(short = 'v', long = "devhub")]
+        devhub: Option<String>,
+        #[arg(long = "dry-run", help = "List the changes without writing them")]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum DepsCommands {
+    #[command(about = "Prints the dependency tree of each package, with the versions in ht.lock")]
+    Tree {
+        #[arg(long = "format", value_enum, default_value_t = commands::deps::Format::Text)]
+        format: commands::deps::Format,
+    },
+    #[command(about = "Explains which packages pull in a dependency")]
+    Why {
+        package: String,
+        #[arg(long = "format", value_enum, default_value_t = commands::deps::Format::Text)]
+        format: commands::deps::Format,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    #[command(about = "Prints the effective settings and where each value came from")]
+    Show {},
+}
+
+impl Commands {
+    // the commands that only read and write project files work without sf
+    fn needs_sf(&self) -> bool {
+        !matches!(
+            self,
+            Commands::LintProject {}
+                | Commands::Release {}
+                | Commands::Config { .. }
+                | Commands::Deps { .. }
+                | Commands::Lock { check: true, .. }
+                | Commands::Alias {
+                    command: AliasCommands::List {}
+                        | AliasCommands::Add { .. }
+                        | AliasCommands::Remove { .. }
+                }
+        )
+    }
 }
 
 fn main() -> Result<()> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info"))
+        .format_timestamp(None)
+        .format_target(false)
+        .init();
     let cli = Cli::parse();
-    match sf::verify_cli_is_installed() {
-        Ok(_) => (),
-        Err(x) => return Err(anyhow!(x)),
+    // every path ht uses is relative to the project root
+    let project_root = match &cli.project_dir {
+        Some(x) if !x.join(project_config::PROJECT_FILE).is_file() => {
+            return Err(project_config::ConfigError::NotFound {
+                path: x.join(project_config::PROJECT_FILE).display().to_string(),
+            }
+            .into())
+        }
+        Some(x) => x.to_owned(),
+        None => project_config::find_root(&env::current_dir()?)?,
+    };
+    env::set_current_dir(&project_root)
+        .map_err(|e| anyhow!("could not open {}: {}", project_root.display(), e))?;
+
+    // linting has to work on a project json that cannot be read
+    if let Commands::LintProject {} = &cli.command {
+        return commands::lint_project::run(Path::new(project_config::PROJECT_FILE));
     }
-    let mut project_config = project_config::read(None);
+    if cli.command.needs_sf() {
+        sf::verify_cli_is_installed()?;
+    }
+    let mut project_config = project_config::read(None)?;
+    let ht_config = config::load(&project_config)?;
+    let environment = cli.env.clone().or_else(|| env::var("HT_ENV").ok());
+    let mut settings = ht_config.get_settings(environment.as_deref(), |x| env::var(x).ok())?;
 
     match &cli.command {
-        Commands::Verify { devhub, target_org } => {
+        Commands::Verify {
+            devhub,
+            target_org,
+            definition_file,
+            duration_days,
+            edition,
+            no_namespace,
+            keep,
+            keep_on_failure,
+            approve,
+            update,
+        } => {
+            settings.devhub.set_flag(devhub.clone(), "--devhub");
             let scratch_org_name = format!(
                 "{}{}",
                 project_config.get_name(),
                 rand::thread_rng().gen::<usize>()
             );
             println!("scratch name {}", scratch_org_name);
-            let command_run =
-                commands::verify::run(&scratch_org_name, devhub, target_org, &mut project_config);
+            let scratch_options = sf::ScratchOrgOptions {
+                definition_file: definition_file.to_owned(),
+                duration_days: *duration_days,
+                edition: edition.to_owned(),
+                no_namespace: *no_namespace,
+            };
+            let command_run = commands::verify::run(
+                &scratch_org_name,
+                target_org,
+                &scratch_options,
+                &ht_config,
+                &settings,
+                &commands::verify::VerifyOptions {
+                    approved: *approve,
+                    update_lock: *update,
+                },
+                &mut project_config,
+            );
 
             if target_org.is_none() {
-                sf::SalesforceCli::new(Some(scratch_org_name.to_owned())).delete_old_scratch()?;
+                if *keep || (*keep_on_failure && command_run.is_err()) {
+                    if let Err(e) = commands::verify::keep_scratch_org(
+                        &scratch_org_name,
+                        &settings.devhub.value,
+                    ) {
+                        warn!("Could not keep scratch org {}: {:#}", scratch_org_name, e);
+                    }
+                } else {
+                    sf::SalesforceCli::new(Some(scratch_org_name.to_owned()))
+                        .delete_old_scratch()?;
+                }
             }
 
-            if command_run.is_err() {
-                return Err(anyhow!(command_run.unwrap_err()));
-            }
-
-            Ok(())
+            command_run
         }
         Commands::Version {
             dry_run,
             devhub,
             push,
-        } => commands::version::run(&mut project_config, dry_run, push, devhub),
-        Commands::Release {} => {
-            todo!()
+        } => {
+            settings.devhub.set_flag(devhub.clone(), "--devhub");
+            commands::version::run(&mut project_config, dry_run, push, &settings)
+        }
+        Commands::Release {} => Err(anyhow!(
+            "ht release is not supported yet, use ht version and ht deploy to release a package"
+        )),
+        Commands::Validate {
+            target_org,
+            test_level,
+            approve,
+        } => {
+            settings
+                .target_org
+                .set_flag(target_org.clone().map(Some), "--target-org");
+            settings
+                .test_level
+                .set_flag(test_level.clone(), "--test-level");
+            let target_org = settings.get_target_org()?;
+            commands::validate::run(&target_org, &settings, *approve, &project_config)
+        }
+        Commands::Deploy {
+            target_org,
+            test_level,
+            quick,
+            since,
+            until,
+            destructive_phase,
+            allow_destructive,
+            approve,
+        } => {
+            settings
+                .target_org
+                .set_flag(target_org.clone().map(Some), "--target-org");
+            settings
+                .test_level
+                .set_flag(test_level.clone(), "--test-level");
+            settings.allow_destructive.set_flag(
+                allow_destructive.then_some(Some(true)),
+                "--allow-destructive",
+            );
+            let target_org = settings.get_target_org()?;
+            let delta = since.as_ref().map(|since| commands::deploy::DeltaOptions {
+                since: since.to_owned(),
+                until: until.to_owned(),
+                destructive_phase: *destructive_phase,
+                allow_destructive: settings.allow_destructive.value,
+            });
+            commands::deploy::run(
+                &target_org,
+                quick,
+                &delta,
+                &settings,
+                *approve,
+                &project_config,
+            )
+        }
+        Commands::LintProject {} => unreachable!(),
+        Commands::Org { command } => match command {
+            OrgCommands::Prune {
+                devhub,
+                max_age_hours,
+                dry_run,
+            } => {
+                settings.devhub.set_flag(devhub.clone(), "--devhub");
+                commands::org::prune(
+                    &settings.devhub.value,
+                    *max_age_hours,
+                    *dry_run,
+                    &project_config,
+                )
+            }
+        },
+        Commands::Alias { command } => match command {
+            AliasCommands::List {} => commands::alias::list(&project_config),
+            AliasCommands::Add { alias, id } => {
+                commands::alias::add(alias, id, &mut project_config)
+            }
+            AliasCommands::Remove { alias } => commands::alias::remove(alias, &mut project_config),
+            AliasCommands::Sync { devhub, dry_run } => {
+                settings.devhub.set_flag(devhub.clone(), "--devhub");
+                commands::alias::sync(&settings.devhub.value, *dry_run, &mut project_config)
+            }
+        },
+        Commands::Deps { command } => match command {
+            DepsCommands::Tree { format } => commands::deps::tree(*format, &project_config),
+            DepsCommands::Why { package, format } => {
+                commands::deps::why(package, *format, &project_config)
+            }
+        },
+        Commands::Lock { devhub, check } => {
+            settings.devhub.set_flag(devhub.clone(), "--devhub");
+            commands::lock::run(&settings.devhub.value, *check, &mut project_config)
         }
+        Commands::Config { command } => match command {
+            ConfigCommands::Show {} => commands::config::show(&settings),
+        },
     }
 }
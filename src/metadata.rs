@@ -0,0 +1,328 @@
+use anyhow::Result;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Component as PathComponent, Path, PathBuf},
+};
+
+// source format folders and the metadata type of the components inside them
+const TYPE_BY_FOLDER: &[(&str, &str)] = &[
+    ("applications", "CustomApplication"),
+    ("aura", "AuraDefinitionBundle"),
+    ("classes", "ApexClass"),
+    ("components", "ApexComponent"),
+    ("contentassets", "ContentAsset"),
+    ("cspTrustedSites", "CspTrustedSite"),
+    ("customMetadata", "CustomMetadata"),
+    ("customPermissions", "CustomPermission"),
+    ("flexipages", "FlexiPage"),
+    ("flows", "Flow"),
+    ("globalValueSets", "GlobalValueSet"),
+    ("groups", "Group"),
+    ("labels", "CustomLabels"),
+    ("layouts", "Layout"),
+    ("lwc", "LightningComponentBundle"),
+    ("namedCredentials", "NamedCredential"),
+    ("notificationtypes", "CustomNotificationType"),
+    ("objects", "CustomObject"),
+    ("pages", "ApexPage"),
+    ("permissionsetgroups", "PermissionSetGroup"),
+    ("permissionsets", "PermissionSet"),
+    ("profiles", "Profile"),
+    ("queues", "Queue"),
+    ("quickActions", "QuickAction"),
+    ("remoteSiteSettings", "RemoteSiteSetting"),
+    ("standardValueSets", "StandardValueSet"),
+    ("staticresources", "StaticResource"),
+    ("tabs", "CustomTab"),
+    ("triggers", "ApexTrigger"),
+];
+
+// folders inside objects/<Object> and the metadata type of their components
+const CHILD_TYPE_BY_FOLDER: &[(&str, &str)] = &[
+    ("businessProcesses", "BusinessProcess"),
+    ("compactLayouts", "CompactLayout"),
+    ("fieldSets", "FieldSet"),
+    ("fields", "CustomField"),
+    ("listViews", "ListView"),
+    ("recordTypes", "RecordType"),
+    ("validationRules", "ValidationRule"),
+    ("webLinks", "WebLink"),
+];
+
+const BUNDLE_FOLDERS: &[&str] = &["aura", "lwc"];
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Component {
+    pub component_type: String,
+    pub member: String,
+}
+
+// a component resolved from one of its source files, with the path that
+// has to exist for the component to still exist in the project
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceComponent {
+    pub component: Component,
+    pub root: PathBuf,
+}
+
+pub fn resolve(path: &Path) -> Option<SourceComponent> {
+    let parts = path
+        .components()
+        .filter_map(|x| match x {
+            PathComponent::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let (index, component_type) = parts.iter().enumerate().find_map(|(i, part)| {
+        TYPE_BY_FOLDER
+            .iter()
+            .find(|(folder, _)| folder == part)
+            .map(|(_, component_type)| (i, *component_type))
+    })?;
+    let folder = parts[index];
+    let base = parts[..=index].iter().collect::<PathBuf>();
+    let rest = &parts[index + 1..];
+    if rest.is_empty() || rest.iter().any(|x| x.starts_with('.') || *x == "__tests__") {
+        return None;
+    }
+
+    if BUNDLE_FOLDERS.contains(&folder) {
+        if rest.len() < 2 {
+            return None;
+        }
+        return Some(SourceComponent {
+            component: Component::new(component_type, rest[0]),
+            root: base.join(rest[0]),
+        });
+    }
+
+    match folder {
+        "objects" => resolve_object(&base, rest),
+        "staticresources" => {
+            let name = rest[0].split('.').next()?;
+            Some(SourceComponent {
+                component: Component::new(component_type, name),
+                root: base.join(format!("{}.resource-meta.xml", name)),
+            })
+        }
+        _ => {
+            let file_name = rest[rest.len() - 1];
+            let directory = base.join(rest[..rest.len() - 1].iter().collect::<PathBuf>());
+            let (member, meta_file_name) = get_member_name(file_name)?;
+            Some(SourceComponent {
+                component: Component::new(component_type, &member),
+                root: directory.join(meta_file_name),
+            })
+        }
+    }
+}
+
+fn resolve_object(base: &Path, rest: &[&str]) -> Option<SourceComponent> {
+    let object_name = rest[0];
+    match rest {
+        [_, file_name] => {
+            if *file_name != format!("{}.object-meta.xml", object_name) {
+                return None;
+            }
+            Some(SourceComponent {
+                component: Component::new("CustomObject", object_name),
+                root: base.join(object_name).join(file_name),
+            })
+        }
+        [_, child_folder, file_name] => {
+            let (_, child_type) = CHILD_TYPE_BY_FOLDER
+                .iter()
+                .find(|(folder, _)| folder == child_folder)?;
+            let (member, meta_file_name) = get_member_name(file_name)?;
+            Some(SourceComponent {
+                component: Component::new(child_type, &format!("{}.{}", object_name, member)),
+                root: base
+                    .join(object_name)
+                    .join(child_folder)
+                    .join(meta_file_name),
+            })
+        }
+        _ => None,
+    }
+}
+
+// "Foo.cls" and "Foo.cls-meta.xml" are both the ApexClass "Foo" described by "Foo.cls-meta.xml"
+fn get_member_name(file_name: &str) -> Option<(String, String)> {
+    let source_file_name = file_name.strip_suffix("-meta.xml").unwrap_or(file_name);
+    let (member, _suffix) = source_file_name.rsplit_once('.')?;
+    Some((member.to_owned(), format!("{}-meta.xml", source_file_name)))
+}
+
+impl Component {
+    pub fn new(component_type: &str, member: &str) -> Component {
+        Component {
+            component_type: component_type.to_owned(),
+            member: member.to_owned(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Manifest {
+    members_by_type: BTreeMap<String, BTreeSet<String>>,
+    version: String,
+}
+
+impl Manifest {
+    pub fn new(version: &str) -> Manifest {
+        Manifest {
+            members_by_type: BTreeMap::new(),
+            version: version.to_owned(),
+        }
+    }
+
+    pub fn add(&mut self, component: &Component) {
+        self.members_by_type
+            .entry(component.component_type.clone())
+            .or_default()
+            .insert(component.member.clone());
+    }
+
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<Package xmlns=\"http://soap.sforce.com/2006/04/metadata\">\n",
+        );
+        for (component_type, members) in &self.members_by_type {
+            xml.push_str("    <types>\n");
+            for member in members {
+                xml.push_str(&format!(
+                    "        <members>{}</members>\n",
+                    escape_xml(member)
+                ));
+            }
+            xml.push_str(&format!("        <name>{}</name>\n", component_type));
+            xml.push_str("    </types>\n");
+        }
+        xml.push_str(&format!("    <version>{}</version>\n", self.version));
+        xml.push_str("</Package>\n");
+        xml
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_xml())?;
+        Ok(())
+    }
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve_component(path: &str) -> Option<Component> {
+        resolve(Path::new(path)).map(|x| x.component)
+    }
+
+    #[test]
+    fn it_should_resolve_source_files_and_their_meta_files() {
+        let expected = Some(Component::new("ApexClass", "Fake"));
+        assert_eq!(
+            expected,
+            resolve_component("force-app/main/default/classes/Fake.cls")
+        );
+        assert_eq!(
+            expected,
+            resolve_component("force-app/main/default/classes/Fake.cls-meta.xml")
+        );
+        assert_eq!(
+            PathBuf::from("force-app/main/default/classes/Fake.cls-meta.xml"),
+            resolve(Path::new("force-app/main/default/classes/Fake.cls"))
+                .unwrap()
+                .root
+        );
+        assert_eq!(
+            Some(Component::new("Layout", "Account-Account Layout")),
+            resolve_component(
+                "force-app/main/default/layouts/Account-Account Layout.layout-meta.xml"
+            )
+        );
+    }
+
+    #[test]
+    fn it_should_resolve_bundles() {
+        let resolved = resolve(Path::new(
+            "force-app/main/default/lwc/fakeComponent/fakeComponent.js",
+        ))
+        .unwrap();
+        assert_eq!(
+            Component::new("LightningComponentBundle", "fakeComponent"),
+            resolved.component
+        );
+        assert_eq!(
+            PathBuf::from("force-app/main/default/lwc/fakeComponent"),
+            resolved.root
+        );
+        assert_eq!(
+            None,
+            resolve_component("force-app/main/default/lwc/.eslintrc.json")
+        );
+        assert_eq!(
+            None,
+            resolve_component(
+                "force-app/main/default/lwc/fakeComponent/__tests__/fakeComponent.test.js"
+            )
+        );
+    }
+
+    #[test]
+    fn it_should_resolve_object_children() {
+        assert_eq!(
+            Some(Component::new("CustomObject", "Fake__c")),
+            resolve_component("force-app/main/default/objects/Fake__c/Fake__c.object-meta.xml")
+        );
+        assert_eq!(
+            Some(Component::new("CustomField", "Fake__c.Amount__c")),
+            resolve_component(
+                "force-app/main/default/objects/Fake__c/fields/Amount__c.field-meta.xml"
+            )
+        );
+        assert_eq!(
+            Some(Component::new("StaticResource", "logo")),
+            resolve_component("force-app/main/default/staticresources/logo.png")
+        );
+        assert_eq!(
+            None,
+            resolve_component("force-app/main/default/reports/Fake.report-meta.xml")
+        );
+    }
+
+    #[test]
+    fn it_should_write_a_package_xml() {
+        let mut manifest = Manifest::new("61.0");
+        manifest.add(&Component::new("ApexClass", "Fake"));
+        manifest.add(&Component::new("ApexClass", "FakeTest"));
+        manifest.add(&Component::new("CustomField", "Fake__c.Amount__c"));
+        assert_eq!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<Package xmlns="http://soap.sforce.com/2006/04/metadata">
+    <types>
+        <members>Fake</members>
+        <members>FakeTest</members>
+        <name>ApexClass</name>
+    </types>
+    <types>
+        <members>Fake__c.Amount__c</members>
+        <name>CustomField</name>
+    </types>
+    <version>61.0</version>
+</Package>
+"#,
+            manifest.to_xml()
+        );
+    }
+}
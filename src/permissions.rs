@@ -0,0 +1,68 @@
+use anyhow::Result;
+use std::fs;
+
+use crate::cli::sf::SalesforceCli;
+use crate::config::PackageSettings;
+use crate::state;
+
+// licenses first, the permission sets may depend on them
+pub fn assign(cli: &mut SalesforceCli, settings: &PackageSettings) -> Result<()> {
+    if !settings.permission_set_licenses.is_empty() {
+        cli.assign_permission_set_licenses(&settings.permission_set_licenses)?;
+    }
+    if !settings.permission_sets.is_empty() {
+        cli.assign_permission_sets(&settings.permission_sets)?;
+    }
+    if !settings.permission_set_groups.is_empty() {
+        let path = state::get_dir("rendered")?.join("assign-permission-set-groups.apex");
+        fs::write(
+            &path,
+            get_group_assignment_script(&settings.permission_set_groups),
+        )?;
+        cli.exec_anonymous(&path.to_string_lossy(), None)?;
+    }
+    Ok(())
+}
+
+// sf has no command to assign permission set groups, the running user assigns them to itself
+fn get_group_assignment_script(names: &[String]) -> String {
+    let names = names
+        .iter()
+        .map(|x| format!("'{}'", x.replace('\\', "\\\\").replace('\'', "\\'")))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        r#"Set<String> names = new Set<String>{{ {names} }};
+List<PermissionSetGroup> groups = [SELECT Id FROM PermissionSetGroup WHERE DeveloperName IN :names];
+System.assertEquals(names.size(), groups.size(), 'Permission set groups not found: ' + names);
+Set<Id> assigned = new Set<Id>();
+for (PermissionSetAssignment assignment : [SELECT PermissionSetGroupId FROM PermissionSetAssignment WHERE AssigneeId = :UserInfo.getUserId() AND PermissionSetGroupId IN :groups]) {{
+    assigned.add(assignment.PermissionSetGroupId);
+}}
+List<PermissionSetAssignment> assignments = new List<PermissionSetAssignment>();
+for (PermissionSetGroup permissionSetGroup : groups) {{
+    if (!assigned.contains(permissionSetGroup.Id)) {{
+        assignments.add(new PermissionSetAssignment(AssigneeId = UserInfo.getUserId(), PermissionSetGroupId = permissionSetGroup.Id));
+    }}
+}}
+insert assignments;
+"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_only_assign_missing_permission_set_groups() {
+        let script = get_group_assignment_script(&[
+            String::from("Expense_Approvers"),
+            String::from("O'Brien"),
+        ]);
+        assert!(script.starts_with(
+            "Set<String> names = new Set<String>{ 'Expense_Approvers', 'O\\'Brien' };\n"
+        ));
+        assert!(script.contains("if (!assigned.contains(permissionSetGroup.Id))"));
+    }
+}
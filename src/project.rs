@@ -1,95 +1,700 @@
-use anyhow::Result;
-use std::{ffi::OsStr, fs};
+use anyhow::{anyhow, Result};
+use git2::Repository;
+use log::{info, warn};
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    fs,
+    path::{Component, Path, PathBuf},
+    time::Duration,
+};
 
-use crate::cli::sf::SalesforceCli;
+use crate::cli::sf::{CliResult, SalesforceCli};
+use crate::config::Settings;
+use crate::project_config::Package;
+use crate::state;
+use crate::template;
 
 use super::system;
 
+const VARS_PATH: &str = "deploy/vars.json";
+
+#[derive(Debug, PartialEq)]
 enum ScriptType {
     Shell,
     Apex,
+    Soql,
+    DataPlan,
+    Node,
+    Python,
     Unknown,
 }
 
-pub struct Script {
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    Pre,
+    Post,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HookCommand {
+    Verify,
+    Deploy,
+}
+
+#[derive(Deserialize, Debug)]
+struct ScriptManifest {
+    steps: Vec<Step>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Step {
     path: String,
-    s_type: ScriptType,
+    phase: Phase,
+    // runs around the deployment of this package instead of around the whole deployment
+    package: Option<String>,
+    #[serde(default)]
+    continue_on_error: bool,
+    // in seconds, only enforced for shell, node and python scripts
+    timeout: Option<u64>,
+    #[serde(default = "get_default_commands")]
+    commands: Vec<HookCommand>,
+}
+
+fn get_default_commands() -> Vec<HookCommand> {
+    vec![HookCommand::Verify]
+}
+
+// what the scripts are running against, passed to shell scripts as HT_* environment variables
+#[derive(Debug, Clone)]
+pub struct HookContext {
+    pub target_org: String,
+    pub devhub: String,
+}
+
+// the deployment scripts that run for one ht command
+pub struct Hooks {
+    steps: Vec<Step>,
+    context: HookContext,
+    git_sha: Option<String>,
+    // values from the vars file, available to apex templates as {{ vars.<name> }}
+    vars: BTreeMap<String, String>,
+    // usernames of the users created for the run by alias
+    users: BTreeMap<String, String>,
+    // the package of steps that are not scoped to one
+    default_package: Option<Package>,
 }
 
-pub fn exec_predeploy_scripts(cli: SalesforceCli) -> Result<()> {
-    match get_predeploy_scripts() {
-        Ok(x) => {
-            exec_scripts(x, cli);
-            Ok(())
+impl Hooks {
+    pub fn load(
+        command: HookCommand,
+        context: HookContext,
+        settings: &Settings,
+        packages: &[Package],
+    ) -> Result<Hooks> {
+        let manifest = Path::new(&settings.script_manifest.value);
+        let steps = if manifest.exists() {
+            let steps = Self::read_manifest(manifest)?;
+            check_packages(&steps, packages)?;
+            steps
+        } else {
+            let mut steps = get_steps_in(&settings.pre_scripts_dir.value, Phase::Pre)?;
+            steps.append(&mut get_steps_in(
+                &settings.post_scripts_dir.value,
+                Phase::Post,
+            )?);
+            steps
+        };
+
+        Ok(Hooks {
+            steps: steps
+                .into_iter()
+                .filter(|x| x.commands.contains(&command))
+                .collect(),
+            context,
+            git_sha: get_git_sha(),
+            vars: read_vars(Path::new(VARS_PATH))?,
+            users: BTreeMap::new(),
+            default_package: packages.iter().find(|x| x.default != Some(false)).cloned(),
+        })
+    }
+
+    pub fn add_user(&mut self, alias: &str, username: &str) {
+        self.users.insert(alias.to_owned(), username.to_owned());
+    }
+
+    fn read_manifest(path: &Path) -> Result<Vec<Step>> {
+        let manifest: ScriptManifest = serde_json::from_str(&fs::read_to_string(path)?)
+            .map_err(|e| anyhow!("{} is not in expected format: {}", path.display(), e))?;
+        Ok(manifest.steps)
+    }
+
+    fn get_steps(&self, phase: Phase, package: Option<&Package>) -> Vec<&Step> {
+        self.steps
+            .iter()
+            .filter(|x| {
+                x.phase == phase && x.package.as_deref() == package.map(|x| x.name.as_str())
+            })
+            .collect()
+    }
+
+    pub fn run(
+        &self,
+        phase: Phase,
+        package: Option<&Package>,
+        cli: &mut SalesforceCli,
+    ) -> Result<()> {
+        let env = self.get_env(phase, package);
+        let variables = self.get_variables(&env);
+        for step in self.get_steps(phase, package) {
+            if let Err(e) = exec_step(step, cli, &env, &variables) {
+                if !step.continue_on_error {
+                    return Err(e.context(format!("deployment script {} failed", step.path)));
+                }
+                println!(
+                    "Deployment script {} failed, continuing: {:#}",
+                    step.path, e
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn get_env(&self, phase: Phase, package: Option<&Package>) -> Vec<(String, String)> {
+        let mut env = vec![
+            (
+                String::from("HT_TARGET_ORG"),
+                self.context.target_org.clone(),
+            ),
+            (String::from("HT_DEVHUB"), self.context.devhub.clone()),
+            (
+                String::from("HT_PHASE"),
+                String::from(match phase {
+                    Phase::Pre => "pre",
+                    Phase::Post => "post",
+                }),
+            ),
+        ];
+        if let Some(git_sha) = &self.git_sha {
+            env.push((String::from("HT_GIT_SHA"), git_sha.clone()));
+        }
+        for (alias, username) in &self.users {
+            env.push((get_user_variable(alias), username.clone()));
+        }
+        if let Some(package) = package.or(self.default_package.as_ref()) {
+            env.push((String::from("HT_PACKAGE"), package.name.clone()));
+            env.push((String::from("HT_PACKAGE_PATH"), package.path.clone()));
+            env.push((String::from("HT_VERSION"), package.version_number.clone()));
+        }
+        env
+    }
+
+    // the HT_* environment variables become target_org, package, git_sha... in apex templates
+    fn get_variables(&self, env: &[(String, String)]) -> BTreeMap<String, String> {
+        let mut variables = env
+            .iter()
+            .filter_map(|(name, value)| {
+                name.strip_prefix("HT_")
+                    .map(|x| (x.to_lowercase(), value.clone()))
+            })
+            .collect::<BTreeMap<_, _>>();
+        for (name, value) in &self.vars {
+            variables.insert(format!("vars.{}", name), value.clone());
         }
-        Err(_) => Ok(()),
+        variables
+    }
+
+    // runs the steps for the whole deployment and then the steps of each deployed package
+    pub fn run_all(
+        &self,
+        phase: Phase,
+        packages: &[&Package],
+        cli: &mut SalesforceCli,
+    ) -> Result<()> {
+        self.run(phase, None, cli)?;
+        for package in packages {
+            self.run(phase, Some(package), cli)?;
+        }
+        Ok(())
     }
 }
 
-fn exec_scripts(scripts: Vec<Script>, mut cli: SalesforceCli) {
-    for script in scripts {
-        match script.s_type {
-            ScriptType::Apex => {
-                cli.exec_anonymous(&script.path);
-            }
-            ScriptType::Shell => {
-                system::exec_script(&script.path);
-            }
-            ScriptType::Unknown => {
-                println!("Script is not an accepted type")
+// a step for a package that is not in sfdx-project.json would never run
+fn check_packages(steps: &[Step], packages: &[Package]) -> Result<()> {
+    for step in steps {
+        if let Some(package) = &step.package {
+            if !packages.iter().any(|x| &x.name == package) {
+                return Err(anyhow!(
+                    "deployment script {} is for package {} which is not in sfdx-project.json",
+                    step.path,
+                    package
+                ));
             }
         }
     }
+    Ok(())
 }
 
-pub fn exec_postdeploy_scripts(cli: SalesforceCli) -> Result<()> {
-    match get_postdeploy_scripts() {
-        Ok(x) => {
-            exec_scripts(x, cli);
-            Ok(())
+fn exec_step(
+    step: &Step,
+    cli: &mut SalesforceCli,
+    env: &[(String, String)],
+    variables: &BTreeMap<String, String>,
+) -> Result<()> {
+    match get_script_type(Path::new(&step.path)) {
+        ScriptType::Apex => {
+            let rendered_path = render_apex(&step.path, variables)?;
+            let log_file = get_log_file(&step.path)?;
+            cli.exec_anonymous(&rendered_path.to_string_lossy(), Some(&log_file))?;
+            info!(
+                "Apex debug log for {} written to {}",
+                step.path,
+                log_file.display()
+            );
+        }
+        ScriptType::Shell => {
+            system::exec_script("sh", &step.path, step.timeout.map(Duration::from_secs), env)?;
+        }
+        ScriptType::Node => {
+            system::exec_script(
+                "node",
+                &step.path,
+                step.timeout.map(Duration::from_secs),
+                env,
+            )?;
+        }
+        ScriptType::Python => {
+            system::exec_script(
+                "python3",
+                &step.path,
+                step.timeout.map(Duration::from_secs),
+                env,
+            )?;
+        }
+        ScriptType::Soql => {
+            let command_output = cli.query(&read_soql(&step.path)?, false)?;
+            if let Some(CliResult::Query { records, .. }) = &command_output.result {
+                let dir = state::get_dir("results")?;
+                let name = get_file_stem(&step.path);
+                fs::write(
+                    dir.join(format!("{}.json", name)),
+                    serde_json::to_string_pretty(records)?,
+                )?;
+                fs::write(dir.join(format!("{}.csv", name)), to_csv(records))?;
+                info!(
+                    "{} records from {} written to {}",
+                    records.len(),
+                    step.path,
+                    dir.display()
+                );
+            }
+        }
+        ScriptType::DataPlan => {
+            cli.import_data_tree(&step.path)?;
+        }
+        ScriptType::Unknown => {
+            warn!(
+                "Skipping {}: scripts must be .apex, .sh, .soql, .js or .py files or -plan.json data import plans",
+                step.path
+            );
         }
-        Err(_) => Ok(()),
     }
+    Ok(())
 }
 
-fn get_predeploy_scripts() -> Result<Vec<Script>> {
-    let paths = fs::read_dir("deploy/pre")?;
-    Ok(get_scripts(paths))
+// the rendered script is kept in .ht/rendered so it can be checked when the script fails, under
+// its own path so deploy/pre/setup.apex and deploy/post/setup.apex do not overwrite each other
+fn render_apex(path: &str, variables: &BTreeMap<String, String>) -> Result<PathBuf> {
+    let rendered = template::render(&fs::read_to_string(path)?, variables)
+        .map_err(|e| anyhow!("could not render {}: {}", path, e))?;
+    let rendered_path = state::get_dir("rendered")?.join(get_relative_path(path));
+    if let Some(dir) = rendered_path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&rendered_path, rendered)?;
+    info!("Rendered {} to {}", path, rendered_path.display());
+    Ok(rendered_path)
 }
 
-fn get_scripts(paths: fs::ReadDir) -> Vec<Script> {
-    let mut scripts: Vec<Script> = Vec::new();
+// ./deploy/pre/setup.apex and ../deploy/pre/setup.apex both become deploy/pre/setup.apex
+fn get_relative_path(path: &str) -> PathBuf {
+    Path::new(path)
+        .components()
+        .filter_map(|x| match x {
+            Component::Normal(x) => Some(x),
+            _ => None,
+        })
+        .collect()
+}
 
-    paths.for_each(|entry| {
-        if let Ok(entry) = entry {
-            let s_type = if get_extension(&entry) == "apex" {
-                ScriptType::Apex
-            } else if get_extension(&entry) == "sh" {
-                ScriptType::Shell
+// the user aliased "qa-approver" is HT_USER_QA_APPROVER
+fn get_user_variable(alias: &str) -> String {
+    format!(
+        "HT_USER_{}",
+        alias
+            .chars()
+            .map(|x| if x.is_ascii_alphanumeric() {
+                x.to_ascii_uppercase()
             } else {
-                ScriptType::Unknown
-            };
+                '_'
+            })
+            .collect::<String>()
+    )
+}
+
+fn read_vars(path: &Path) -> Result<BTreeMap<String, String>> {
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let vars: BTreeMap<String, Value> = serde_json::from_str(&fs::read_to_string(path)?)
+        .map_err(|e| anyhow!("{} is not in expected format: {}", path.display(), e))?;
+    Ok(vars
+        .into_iter()
+        .map(|(name, value)| match value {
+            Value::String(x) => (name, x),
+            _ => (name, value.to_string()),
+        })
+        .collect())
+}
 
-            scripts.push(Script {
-                path: entry.path().into_os_string().into_string().unwrap(),
-                s_type,
+fn get_git_sha() -> Option<String> {
+    let repo = Repository::discover(".").ok()?;
+    let commit = repo.head().ok()?.peel_to_commit().ok()?;
+    Some(commit.id().to_string())
+}
+
+// sf does not accept the // comments that .soql files usually start with
+fn read_soql(path: &str) -> Result<String> {
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .map(|x| x.trim())
+        .filter(|x| !x.is_empty() && !x.starts_with("//"))
+        .collect::<Vec<_>>()
+        .join(" "))
+}
+
+fn to_csv(records: &[Value]) -> String {
+    let rows = records.iter().map(flatten_record).collect::<Vec<_>>();
+    let mut columns: Vec<String> = Vec::new();
+    for row in &rows {
+        for (column, _) in row {
+            if !columns.contains(column) {
+                columns.push(column.clone());
+            }
+        }
+    }
+
+    let mut csv = columns
+        .iter()
+        .map(|x| escape_csv(x))
+        .collect::<Vec<_>>()
+        .join(",");
+    csv.push('\n');
+    for row in rows {
+        let values = columns
+            .iter()
+            .map(|column| {
+                row.iter()
+                    .find(|(x, _)| x == column)
+                    .map(|(_, value)| escape_csv(value))
+                    .unwrap_or_default()
             })
+            .collect::<Vec<_>>();
+        csv.push_str(&values.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+// relationship fields come back as nested records, e.g. Owner.Name
+fn flatten_record(record: &Value) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+    if let Value::Object(map) = record {
+        for (key, value) in map {
+            match value {
+                _ if key == "attributes" => {}
+                Value::Object(_) => {
+                    for (child_key, child_value) in flatten_record(value) {
+                        fields.push((format!("{}.{}", key, child_key), child_value));
+                    }
+                }
+                Value::Null => fields.push((key.clone(), String::new())),
+                Value::String(x) => fields.push((key.clone(), x.clone())),
+                _ => fields.push((key.clone(), value.to_string())),
+            }
         }
-    });
+    }
+    fields
+}
 
-    scripts
+fn escape_csv(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
 }
 
-fn get_extension(entry: &fs::DirEntry) -> String {
-    entry
-        .path()
-        .extension()
+fn get_file_stem(path: &str) -> &str {
+    Path::new(path)
+        .file_stem()
         .and_then(OsStr::to_str)
-        .unwrap()
-        .to_owned()
+        .unwrap_or("script")
+}
+
+fn get_log_file(script_path: &str) -> Result<PathBuf> {
+    Ok(state::get_dir("logs")?.join(format!(
+        "{}-{}.log",
+        get_file_stem(script_path),
+        state::now()
+    )))
+}
+
+// without a manifest every script in the directory runs in file name order
+fn get_steps_in(dir: &str, phase: Phase) -> Result<Vec<Step>> {
+    let mut paths = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|x| x.ok())
+            .map(|x| x.path())
+            .filter(|x| x.is_file())
+            .collect::<Vec<PathBuf>>(),
+        Err(_) => return Ok(Vec::new()),
+    };
+    paths.sort();
+
+    Ok(paths
+        .into_iter()
+        .map(|x| Step {
+            path: x.to_string_lossy().to_string(),
+            phase,
+            package: None,
+            continue_on_error: false,
+            timeout: None,
+            commands: get_default_commands(),
+        })
+        .collect())
+}
+
+fn get_script_type(path: &Path) -> ScriptType {
+    let file_name = path.file_name().and_then(OsStr::to_str).unwrap_or_default();
+    if file_name.ends_with("-plan.json") {
+        return ScriptType::DataPlan;
+    }
+    match get_extension(path) {
+        Some("apex") => ScriptType::Apex,
+        Some("sh") => ScriptType::Shell,
+        Some("soql") => ScriptType::Soql,
+        Some("js") => ScriptType::Node,
+        Some("py") => ScriptType::Python,
+        _ => ScriptType::Unknown,
+    }
+}
+
+fn get_extension(path: &Path) -> Option<&str> {
+    path.extension().and_then(OsStr::to_str)
 }
 
-pub fn get_postdeploy_scripts() -> Result<Vec<Script>> {
-    let paths = fs::read_dir("deploy/post")?;
-    Ok(get_scripts(paths))
+// file names to their path in the project, built once for all the failing tests of a run
+pub fn index_source_files() -> BTreeMap<String, String> {
+    let mut index = BTreeMap::new();
+    index_files_in(Path::new("."), &mut index);
+    index
+}
+
+// files in a directory win over files with the same name in its subdirectories
+fn index_files_in(dir: &Path, index: &mut BTreeMap<String, String>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut sub_dirs = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if path.is_dir() {
+            if !name.starts_with('.') && name != "node_modules" && name != "target" {
+                sub_dirs.push(path);
+            }
+        } else {
+            index.entry(name).or_insert_with(|| {
+                let path = path.to_string_lossy().to_string();
+                path.strip_prefix("./").unwrap_or(&path).to_owned()
+            });
+        }
+    }
+    for sub_dir in sub_dirs {
+        index_files_in(&sub_dir, index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project_config;
+
+    #[test]
+    fn it_should_get_the_script_type() {
+        assert_eq!(
+            ScriptType::Apex,
+            get_script_type(Path::new("deploy/pre/a.apex"))
+        );
+        assert_eq!(
+            ScriptType::Shell,
+            get_script_type(Path::new("deploy/pre/a.sh"))
+        );
+        assert_eq!(
+            ScriptType::Soql,
+            get_script_type(Path::new("deploy/pre/a.soql"))
+        );
+        assert_eq!(
+            ScriptType::Node,
+            get_script_type(Path::new("deploy/pre/a.js"))
+        );
+        assert_eq!(
+            ScriptType::Python,
+            get_script_type(Path::new("deploy/pre/a.py"))
+        );
+        assert_eq!(
+            ScriptType::DataPlan,
+            get_script_type(Path::new("deploy/pre/Account-Contact-plan.json"))
+        );
+        assert_eq!(
+            ScriptType::Unknown,
+            get_script_type(Path::new("deploy/pre/a.json"))
+        );
+        assert_eq!(
+            ScriptType::Unknown,
+            get_script_type(Path::new("deploy/pre/Makefile"))
+        );
+    }
+
+    #[test]
+    fn it_should_read_soql_files_without_comments() {
+        assert_eq!(
+            "SELECT Id, Name FROM Account",
+            read_soql("tests/resources/fake-salesforce-project/scripts/soql/account.soql").unwrap()
+        );
+    }
+
+    #[test]
+    fn it_should_convert_query_records_to_csv() {
+        let records: Vec<Value> = serde_json::from_str(
+            r#"[
+  { "attributes": { "type": "Account" }, "Id": "001A", "Name": "Acme, Inc.", "Owner": { "attributes": { "type": "User" }, "Name": "Ann" } },
+  { "attributes": { "type": "Account" }, "Id": "001B", "Name": "Globex", "Owner": null }
+]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            "Id,Name,Owner.Name,Owner\n001A,\"Acme, Inc.\",Ann,\n001B,Globex,,\n",
+            to_csv(&records)
+        );
+    }
+
+    #[test]
+    fn it_should_keep_the_script_path_of_rendered_scripts() {
+        assert_eq!(
+            PathBuf::from("deploy/pre/setup.apex"),
+            get_relative_path("./deploy/pre/setup.apex")
+        );
+        assert_ne!(
+            get_relative_path("deploy/pre/setup.apex"),
+            get_relative_path("deploy/post/setup.apex")
+        );
+        assert_eq!(
+            PathBuf::from("scripts/setup.apex"),
+            get_relative_path("../scripts/setup.apex")
+        );
+    }
+
+    #[test]
+    fn it_should_reject_steps_for_release() {
+        let error =
+            Hooks::read_manifest(Path::new("tests/resources/malformed/release-scripts.json"))
+                .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("unknown variant `release`, expected `verify` or `deploy`"));
+    }
+
+    #[test]
+    fn it_should_read_the_script_manifest() {
+        let steps = Hooks::read_manifest(Path::new("tests/resources/scripts.json")).unwrap();
+        let mut hooks = Hooks {
+            steps: steps
+                .into_iter()
+                .filter(|x| x.commands.contains(&HookCommand::Verify))
+                .collect(),
+            context: HookContext {
+                target_org: String::from("scratch"),
+                devhub: String::from("DevHub"),
+            },
+            git_sha: Some(String::from("abc123")),
+            vars: read_vars(Path::new("tests/resources/vars.json")).unwrap(),
+            users: BTreeMap::new(),
+            default_package: None,
+        };
+        hooks.add_user("qa-approver", "approver@example.com");
+        let project_config = project_config::read(Some(String::from(
+            "tests/resources/sfdx-project-valid.json",
+        )))
+        .unwrap();
+        let package = &project_config.get_packages()[1];
+        hooks.default_package = project_config.get_packages().first().cloned();
+        assert!(check_packages(&hooks.steps, project_config.get_packages()).is_ok());
+        assert_eq!(
+            "deployment script deploy/post/expenses.apex is for package Expense Manager which is not in sfdx-project.json",
+            check_packages(&hooks.steps, &project_config.get_packages()[..1])
+                .unwrap_err()
+                .to_string()
+        );
+
+        let pre_steps = hooks.get_steps(Phase::Pre, None);
+        assert_eq!(
+            vec!["deploy/pre/1-settings.sh", "deploy/pre/2-data.apex"],
+            pre_steps
+                .iter()
+                .map(|x| x.path.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert!(pre_steps[0].continue_on_error);
+        assert_eq!(Some(120), pre_steps[0].timeout);
+
+        let package_steps = hooks.get_steps(Phase::Post, Some(package));
+        assert_eq!(1, package_steps.len());
+        assert_eq!("deploy/post/expenses.apex", package_steps[0].path);
+
+        // release only steps are not part of the verify hooks
+        assert!(hooks.get_steps(Phase::Post, None).is_empty());
+
+        let env = hooks.get_env(Phase::Post, Some(package));
+        assert!(env.contains(&(String::from("HT_TARGET_ORG"), String::from("scratch"))));
+        assert!(env.contains(&(String::from("HT_DEVHUB"), String::from("DevHub"))));
+        assert!(env.contains(&(String::from("HT_PACKAGE"), String::from("Expense Manager"))));
+        assert!(env.contains(&(String::from("HT_PACKAGE_PATH"), String::from("exp-core"))));
+        assert!(env.contains(&(String::from("HT_VERSION"), String::from("3.2.0.NEXT"))));
+        assert!(env.contains(&(String::from("HT_PHASE"), String::from("post"))));
+        assert!(env.contains(&(String::from("HT_GIT_SHA"), String::from("abc123"))));
+        assert!(env.contains(&(
+            String::from("HT_USER_QA_APPROVER"),
+            String::from("approver@example.com")
+        )));
+
+        // steps that are not package scoped get the default package
+        let env = hooks.get_env(Phase::Pre, None);
+        assert!(env.contains(&(String::from("HT_PACKAGE"), String::from("Test - Package"))));
+        assert!(env.contains(&(String::from("HT_VERSION"), String::from("1.0.0.NEXT"))));
+
+        let env = hooks.get_env(Phase::Post, Some(package));
+        let variables = hooks.get_variables(&env);
+        assert_eq!("scratch", variables["target_org"]);
+        assert_eq!("3.2.0.NEXT", variables["version"]);
+        assert_eq!("admin@example.com", variables["vars.admin_username"]);
+        assert_eq!("3", variables["vars.retries"]);
+        assert_eq!("approver@example.com", variables["user_qa_approver"]);
+    }
 }
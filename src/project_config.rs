@@ -1,52 +1,205 @@
+use crate::lint::{self, Severity};
 use anyhow::anyhow;
 use anyhow::Result;
 use core::fmt;
+use indexmap::IndexMap;
+use log::warn;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::borrow::BorrowMut;
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
     fs::{self},
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
 };
 
-#[derive(Serialize, Deserialize)]
+// every documented sfdx-project.json property, anything else is kept in `extra`
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 struct ProjectJson {
-    name: String,
     package_directories: Vec<PackageDirectory>,
-    package_aliases: Option<HashMap<String, String>>,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    namespace: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sfdc_login_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signup_target_login_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source_api_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    push_package_directories_sequentially: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source_behavior_options: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    replacements: Option<Vec<Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    plugins: Option<IndexMap<String, Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    package_aliases: Option<IndexMap<String, String>>,
+    #[serde(flatten)]
+    extra: IndexMap<String, Value>,
 }
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 struct PackageDirectory {
-    dependencies: Option<Vec<Dependency>>,
-    package: String,
     path: String,
-    version_number: String,
+    // directories without a package only hold source
+    #[serde(skip_serializing_if = "Option::is_none")]
+    package: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version_number: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     version_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     version_description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     default: Option<bool>,
-    unpackaged_metadata: Option<String>, // this is an object for some reason
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dependencies: Option<Vec<Dependency>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ancestor_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ancestor_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unpackaged_metadata: Option<MetadataPath>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed_metadata: Option<MetadataPath>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    apex_test_access: Option<ApexTestAccess>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     release_notes_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     post_install_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    post_install_script: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uninstall_script: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     scope_profiles: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     definition_file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    branch: Option<String>,
+    #[serde(flatten)]
+    extra: IndexMap<String, Value>,
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct MetadataPath {
+    path: String,
+    #[serde(flatten)]
+    extra: IndexMap<String, Value>,
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ApexTestAccess {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    permission_sets: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    permission_set_licenses: Option<Vec<String>>,
+    #[serde(flatten)]
+    extra: IndexMap<String, Value>,
 }
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 struct Dependency {
     package: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     version_number: Option<String>,
+    #[serde(flatten)]
+    extra: IndexMap<String, Value>,
+}
+
+pub const PROJECT_FILE: &str = "sfdx-project.json";
+
+#[derive(Debug)]
+pub enum ConfigError {
+    NoProject {
+        dir: PathBuf,
+    },
+    NotFound {
+        path: String,
+    },
+    Unreadable {
+        path: String,
+        source: io::Error,
+    },
+    Malformed {
+        path: String,
+        source: serde_json::Error,
+    },
+    Invalid {
+        path: String,
+        problems: Vec<String>,
+    },
+    MissingAlias {
+        package: String,
+        dependency: String,
+    },
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::NoProject { dir } => write!(
+                f,
+                "did not find {} in {} or any parent directory",
+                PROJECT_FILE,
+                dir.display()
+            ),
+            ConfigError::NotFound { path } => write!(f, "did not find {}", path),
+            ConfigError::Unreadable { path, source } => {
+                write!(f, "could not read {}: {}", path, source)
+            }
+            ConfigError::Malformed { path, source } => {
+                write!(f, "{} is not in expected format: {}", path, source)
+            }
+            ConfigError::Invalid { path, problems } => write!(
+                f,
+                "{} is not valid, run `ht lint-project` for details:\n{}",
+                path,
+                problems.join("\n")
+            ),
+            ConfigError::MissingAlias {
+                package,
+                dependency,
+            } => write!(
+                f,
+                "dependency {} of package {} is not in packageAliases",
+                dependency, package
+            ),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Unreadable { source, .. } => Some(source),
+            ConfigError::Malformed { source, .. } => Some(source),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct SalesforceProjectConfig {
     name: String,
+    source_api_version: Option<String>,
     packages: Vec<Package>,
+    path: PathBuf,
+    json: ProjectJson,
+    // the file as it was read, so writing it keeps its key order and formatting
+    document: Value,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
 pub struct Package {
     pub name: String,
     pub path: String,
@@ -59,80 +212,105 @@ pub struct Package {
     release_notes_url: Option<String>,
     post_install_url: Option<String>,
     scope_profiles: Option<bool>,
-    definition_file: Option<String>,
+    pub definition_file: Option<String>,
 }
 
 impl Package {
     fn from(
         package_directory: &PackageDirectory,
-        package_aliases: Option<HashMap<String, String>>,
-    ) -> Package {
+        package_aliases: Option<&IndexMap<String, String>>,
+    ) -> Result<Package, ConfigError> {
         let package_directory = package_directory.clone();
-        let dependencies =
-            Self::get_package_dependencies(package_directory.to_owned(), package_aliases);
-        Package {
-            name: package_directory.package,
+        let dependencies = Self::get_package_dependencies(&package_directory, package_aliases)?;
+        Ok(Package {
+            name: package_directory.package.unwrap_or_default(),
             path: package_directory.path,
             version_name: package_directory.version_name,
             version_description: package_directory.version_description,
-            version_number: package_directory.version_number,
-            unpackaged_metadata: package_directory.unpackaged_metadata,
+            version_number: package_directory.version_number.unwrap_or_default(),
+            unpackaged_metadata: package_directory.unpackaged_metadata.map(|x| x.path),
             dependencies,
             default: package_directory.default,
             release_notes_url: package_directory.release_notes_url,
             post_install_url: package_directory.post_install_url,
             scope_profiles: package_directory.scope_profiles,
             definition_file: package_directory.definition_file,
-        }
+        })
     }
 
+    // a dependency is either an alias like "A@1.2.0-1" or a package with a versionNumber
     fn get_package_dependencies(
-        package_directory: PackageDirectory,
-        package_aliases: Option<HashMap<String, String>>,
-    ) -> Option<Vec<PackageDependency>> {
-        if package_directory.dependencies.is_some() {
-            let mut package_dependencies = Vec::new();
-            for dependency in package_directory.dependencies.unwrap().into_iter() {
-                let mut package_dependency = PackageDependency::new();
-                package_dependency.name = dependency.package.to_owned();
-
-                let mut package_dependency = PackageDependency::new();
-                if dependency.version_number.is_some() {
-                    let version_number = &dependency.version_number.unwrap();
-                    let trimmed_version_number = Self::get_version_number_from(version_number);
-                    package_dependency.version = Version::from(trimmed_version_number);
-                    let dependency_name = format!("{}@{}", dependency.package, version_number);
-                    if let Some(version_id) =
-                        package_aliases.to_owned().unwrap().get(&dependency_name)
-                    {
-                        package_dependency.id = version_id.to_string();
-                    }
-                } else {
-                    let version_number =
-                        &dependency.package.split("@").collect::<Vec<&str>>()[1].to_string();
-                    let trimmed_version_number = Self::get_version_number_from(version_number);
-
-                    package_dependency.version = Version::from(trimmed_version_number);
-                    if let Some(version_id) =
-                        package_aliases.to_owned().unwrap().get(&dependency.package)
-                    {
-                        package_dependency.id = version_id.to_string();
+        package_directory: &PackageDirectory,
+        package_aliases: Option<&IndexMap<String, String>>,
+    ) -> Result<Option<Vec<PackageDependency>>, ConfigError> {
+        let Some(dependencies) = &package_directory.dependencies else {
+            return Ok(None);
+        };
+        let empty = IndexMap::new();
+        let package_aliases = package_aliases.unwrap_or(&empty);
+
+        let mut package_dependencies = Vec::new();
+        for dependency in dependencies {
+            let (name, alias_version) = match dependency.package.split_once('@') {
+                Some((name, version)) => (name, Some(version)),
+                None => (dependency.package.as_str(), None),
+            };
+            let version_number = dependency.version_number.as_deref().or(alias_version);
+
+            let mut alias = dependency.package.clone();
+            let id = match &dependency.version_number {
+                Some(version_number) => {
+                    let version_alias = format!("{}@{}", dependency.package, version_number);
+                    match package_aliases.get(&version_alias) {
+                        Some(id) => {
+                            alias = version_alias;
+                            Some(id.clone())
+                        }
+                        // a package id, the version is resolved against the Dev Hub before installing
+                        None => package_aliases.get(&dependency.package).map(|id| {
+                            if id.starts_with("04t") {
+                                id.clone()
+                            } else {
+                                String::new()
+                            }
+                        }),
                     }
                 }
-                package_dependencies.push(package_dependency);
-            }
-            Some(package_dependencies)
-        } else {
-            None
+                None => package_aliases.get(&dependency.package).cloned(),
+            };
+            let Some(id) = id else {
+                return Err(ConfigError::MissingAlias {
+                    package: package_directory.package.clone().unwrap_or_default(),
+                    dependency: dependency.package.clone(),
+                });
+            };
+
+            package_dependencies.push(PackageDependency {
+                name: name.to_owned(),
+                version: version_number
+                    // an invalid versionNumber is warned about when the project is read
+                    .and_then(|x| Version::parse(Self::get_version_number_from(x)))
+                    .unwrap_or(Version::new()),
+                version_number: version_number.map(|x| x.to_owned()),
+                package_id: package_aliases
+                    .get(name)
+                    .filter(|x| x.starts_with("0Ho"))
+                    .cloned(),
+                alias,
+                id,
+            });
         }
+        Ok(Some(package_dependencies))
     }
 
     fn get_version_number_from(source: &str) -> &str {
         source.split('-').next().unwrap()
     }
 
+    // keeps the build number, e.g. 1.2.0.NEXT becomes 1.3.0.NEXT
     pub fn set_version(&mut self, version: &Version) {
-        self.version_number = version.to_string()
+        let build = self.version_number.split('.').nth(3).unwrap_or("NEXT");
+        self.version_number = format!("{}.{}", version, build)
     }
 }
 
@@ -151,19 +329,21 @@ impl Version {
             patch: 0,
         }
     }
-    pub fn from(as_string: &str) -> Version {
-        let version = as_string.split('.').collect::<Vec<&str>>();
-        Version {
-            major: version[0].parse().unwrap(),
-            minor: version[1].parse().unwrap(),
-            patch: version[2].parse().unwrap(),
-        }
+    // missing parts are 0, e.g. 2.0 is 2.0.0, and the build is ignored, parts that are not numbers
+    // are not a version
+    pub fn parse(as_string: &str) -> Option<Version> {
+        let mut parts = as_string.split('.').map(|x| x.parse());
+        let mut next = || parts.next().unwrap_or(Ok(0)).ok();
+        Some(Version {
+            major: next()?,
+            minor: next()?,
+            patch: next()?,
+        })
     }
 
     pub fn is_higher_than(&self, to_compare: &Version) -> bool {
-        self.major > to_compare.major
-            || self.minor > to_compare.minor
-            || self.patch > to_compare.patch
+        (self.major, self.minor, self.patch)
+            > (to_compare.major, to_compare.minor, to_compare.patch)
     }
 }
 
@@ -177,19 +357,16 @@ impl Display for Version {
 pub struct PackageDependency {
     pub name: String,
     version: Version,
+    // as written in sfdx-project.json, e.g. 1.2.0.LATEST
+    pub version_number: Option<String>,
+    // the 0Ho id of the package, used to resolve the version
+    pub package_id: Option<String>,
+    // the packageAliases entry the id came from
+    pub alias: String,
+    // the 04t id when the alias pins a version, empty for a 0Ho alias, see dependencies::resolve
     pub id: String,
 }
 
-impl PackageDependency {
-    fn new() -> PackageDependency {
-        PackageDependency {
-            name: String::from(""),
-            version: Version::new(),
-            id: String::from(""),
-        }
-    }
-}
-
 impl SalesforceProjectConfig {
     pub fn get_name(&self) -> &String {
         &self.name
@@ -198,6 +375,63 @@ impl SalesforceProjectConfig {
         &self.packages
     }
 
+    pub fn get_source_api_version(&self) -> Option<&String> {
+        self.source_api_version.as_ref()
+    }
+
+    pub fn get_plugin_settings(&self, name: &str) -> Option<&Value> {
+        self.json.plugins.as_ref()?.get(name)
+    }
+
+    pub fn get_package_aliases(&self) -> IndexMap<String, String> {
+        self.json.package_aliases.clone().unwrap_or_default()
+    }
+
+    // returns the id the alias had before
+    pub fn set_package_alias(&mut self, alias: &str, id: &str) -> Option<String> {
+        self.json
+            .package_aliases
+            .get_or_insert_with(IndexMap::new)
+            .insert(alias.to_owned(), id.to_owned())
+    }
+
+    pub fn remove_package_alias(&mut self, alias: &str) -> Option<String> {
+        self.json.package_aliases.as_mut()?.shift_remove(alias)
+    }
+
+    // the packages the project builds or depends on, an alias is used when it names one of
+    // them or one of their versions as Name@1.2.0-1
+    pub fn get_referenced_packages(&self) -> BTreeSet<String> {
+        let mut names = BTreeSet::new();
+        for package_directory in &self.json.package_directories {
+            names.extend(package_directory.package.clone());
+            for dependency in package_directory.dependencies.iter().flatten() {
+                names.insert(get_alias_package(&dependency.package).to_owned());
+            }
+        }
+        names
+    }
+
+    // the aliases reading the project looks up, removing one breaks the project
+    pub fn get_required_aliases(&self) -> BTreeSet<String> {
+        let mut aliases = BTreeSet::new();
+        for package_directory in &self.json.package_directories {
+            aliases.extend(package_directory.package.clone());
+            for dependency in package_directory.dependencies.iter().flatten() {
+                aliases.insert(dependency.package.clone());
+                if let Some(version_number) = &dependency.version_number {
+                    aliases.insert(format!("{}@{}", dependency.package, version_number));
+                }
+            }
+        }
+        aliases
+    }
+
+    pub fn is_alias_used(&self, alias: &str) -> bool {
+        self.get_referenced_packages()
+            .contains(get_alias_package(alias))
+    }
+
     pub fn get_dependencies(&mut self) -> Option<Vec<PackageDependency>> {
         let mut dependency_by_name: BTreeMap<String, PackageDependency> = BTreeMap::new();
 
@@ -208,6 +442,14 @@ impl SalesforceProjectConfig {
                     dependency_by_name
                         .entry(dependency.clone().name)
                         .and_modify(|val| {
+                            if dependency.version.major != val.version.major {
+                                warn!(
+                                    "{} is required as {} and {}, using the higher one, see `ht deps tree`",
+                                    dependency.name,
+                                    dependency.version_number.as_deref().unwrap_or_default(),
+                                    val.version_number.as_deref().unwrap_or_default()
+                                );
+                            }
                             if dependency.version.is_higher_than(&val.version) {
                                 *val = dependency.clone();
                             }
@@ -241,6 +483,25 @@ impl SalesforceProjectConfig {
         Err(anyhow!("could not find a default package"))
     }
 
+    // the one place sfdx-project.json is written, with the versions of the packages
+    pub fn write(&mut self) -> Result<()> {
+        for package_directory in self.json.package_directories.iter_mut() {
+            if let Some(package) = self
+                .packages
+                .iter()
+                .find(|x| package_directory.package.as_ref() == Some(&x.name))
+            {
+                package_directory.version_number = Some(package.version_number.clone());
+            }
+        }
+
+        merge(&mut self.document, serde_json::to_value(&self.json)?);
+        let mut content = serde_json::to_string_pretty(&self.document)?;
+        content.push('\n');
+        fs::write(&self.path, content)?;
+        Ok(())
+    }
+
     pub fn get_package(&mut self, name: &str) -> Result<&mut Package> {
         for package in &mut self.packages {
             if package.name == name {
@@ -252,49 +513,271 @@ impl SalesforceProjectConfig {
     }
 }
 
-pub fn read(path: Option<String>) -> SalesforceProjectConfig {
-    let project_json_path = path.unwrap_or(String::from("./sfdx-project.json"));
-    let file = fs::read_to_string(project_json_path).expect("Did not find sfdx-project.json");
-    let json: ProjectJson =
-        serde_json::from_str(&file).expect("SFDX Project JSON is not in expected format");
+// the package part of an alias like Name@1.2.0-1
+pub fn get_alias_package(alias: &str) -> &str {
+    alias.split_once('@').map(|x| x.0).unwrap_or(alias)
+}
+
+// the project root is the closest directory with an sfdx-project.json, like git finds .git
+pub fn find_root(dir: &Path) -> Result<PathBuf, ConfigError> {
+    dir.ancestors()
+        .find(|x| x.join(PROJECT_FILE).is_file())
+        .map(Path::to_path_buf)
+        .ok_or(ConfigError::NoProject {
+            dir: dir.to_path_buf(),
+        })
+}
 
-    let mut project_config = SalesforceProjectConfig {
-        name: json.name,
-        packages: Vec::new(),
+pub fn read(path: Option<String>) -> Result<SalesforceProjectConfig, ConfigError> {
+    let project_json_path = path.unwrap_or(String::from(PROJECT_FILE));
+    let file = fs::read_to_string(&project_json_path).map_err(|e| match e.kind() {
+        ErrorKind::NotFound => ConfigError::NotFound {
+            path: project_json_path.clone(),
+        },
+        _ => ConfigError::Unreadable {
+            path: project_json_path.clone(),
+            source: e,
+        },
+    })?;
+    let malformed = |e| ConfigError::Malformed {
+        path: project_json_path.clone(),
+        source: e,
     };
-
-    for package_directory in json.package_directories.into_iter() {
-        let package = Package::from(
-            &package_directory.to_owned(),
-            json.package_aliases.to_owned(),
+    let document: Value = serde_json::from_str(&file).map_err(malformed)?;
+    let (problems, warnings): (Vec<_>, Vec<_>) = lint::lint(&document, None)
+        .into_iter()
+        .filter(|x| x.severity == Severity::Error)
+        .partition(|x| x.blocks_loading());
+    for warning in warnings {
+        warn!(
+            "{} {}: {} ({}), see `ht lint-project`",
+            project_json_path, warning.path, warning.message, warning.hint
         );
-        project_config.packages.push(package);
     }
+    if !problems.is_empty() {
+        return Err(ConfigError::Invalid {
+            path: project_json_path,
+            problems: problems.iter().map(|x| x.to_string()).collect(),
+        });
+    }
+    let json = serde_json::from_value::<ProjectJson>(document.clone()).map_err(malformed)?;
+    let packages = json
+        .package_directories
+        .iter()
+        .filter(|x| x.package.is_some())
+        .map(|x| Package::from(x, json.package_aliases.as_ref()))
+        .collect::<Result<Vec<_>, _>>()?;
 
-    project_config
+    let project_config = SalesforceProjectConfig {
+        name: json.name.clone(),
+        source_api_version: json.source_api_version.clone(),
+        packages,
+        path: PathBuf::from(project_json_path),
+        json,
+        document,
+    };
+
+    Ok(project_config)
+}
+
+// copies the values of the model over the document, new keys go last and removed keys are dropped,
+// a null the model leaves out because it is None stays
+fn merge(document: &mut Value, model: Value) {
+    match (document, model) {
+        (Value::Object(document), Value::Object(model)) => {
+            let removed = document
+                .iter()
+                .filter(|(key, value)| !model.contains_key(*key) && !value.is_null())
+                .map(|(key, _)| key.clone())
+                .collect::<Vec<_>>();
+            for key in removed {
+                document.shift_remove(&key);
+            }
+            for (key, value) in model {
+                match document.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        document.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Array(document), Value::Array(model)) => {
+            document.truncate(model.len());
+            for (index, value) in model.into_iter().enumerate() {
+                match document.get_mut(index) {
+                    Some(existing) => merge(existing, value),
+                    None => document.push(value),
+                }
+            }
+        }
+        (document, model) => *document = model,
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::read;
+    use super::*;
 
     #[test]
     fn it_should_read_project_json() {
-        let project_config = read(Some(String::from("tests/resources/sfdx-project.json")));
+        let project_config = read(Some(String::from("tests/resources/sfdx-project.json"))).unwrap();
         assert_eq!(2, project_config.get_packages().len());
 
-        assert_eq!("A", project_config.get_packages()[0].name);
-        // assert_eq!("1.0", project_config.get_packages()[0].version.number);
-        // assert_eq!("04tB00000000000000", todo!());
+        let dependencies = project_config.get_packages()[1]
+            .dependencies
+            .as_ref()
+            .unwrap();
+        assert_eq!("A", dependencies[0].name);
+        assert_eq!("1.0.0", dependencies[0].version.to_string());
+        assert_eq!("04tB00000000000000", dependencies[0].id);
+
+        assert_eq!("B@2.0", dependencies[1].alias);
+        assert_eq!("2.0.0", dependencies[1].version.to_string());
+        assert_eq!("04tB00000000000001", dependencies[1].id);
+    }
+
+    #[test]
+    fn it_should_write_project_json_without_losing_anything() {
+        let path =
+            std::env::temp_dir().join(format!("ht-sfdx-project-{}.json", rand::random::<u64>()));
+        let original = fs::read_to_string("tests/resources/sfdx-project-full.json").unwrap();
+        fs::write(&path, &original).unwrap();
+
+        let mut project_config = read(Some(path.to_string_lossy().to_string())).unwrap();
+        // config-only has no package
+        assert_eq!(1, project_config.get_packages().len());
+        assert_eq!(
+            Some(String::from("unpackaged")),
+            project_config.get_packages()[0].unpackaged_metadata
+        );
+        project_config.write().unwrap();
+        assert_eq!(original, fs::read_to_string(&path).unwrap());
+
+        project_config
+            .get_package("Full")
+            .unwrap()
+            .set_version(&Version::parse("1.3.0").unwrap());
+        project_config.write().unwrap();
+        assert_eq!(
+            original.replace("\"1.2.0.NEXT\"", "\"1.3.0.NEXT\""),
+            fs::read_to_string(&path).unwrap()
+        );
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn it_should_read_dependencies_without_a_version_number() {
+        let project_config = read(Some(String::from(
+            "tests/resources/malformed/plain-dependency.json",
+        )))
+        .unwrap();
+        let dependencies = project_config.get_packages()[0]
+            .dependencies
+            .as_ref()
+            .unwrap();
+        assert_eq!("A", dependencies[0].name);
+        assert_eq!("04t000000000001", dependencies[0].id);
+        assert_eq!("B", dependencies[1].name);
+        assert_eq!("2.1.0", dependencies[1].version.to_string());
+        assert_eq!("04t000000000002", dependencies[1].id);
+    }
+
+    #[test]
+    fn it_should_name_the_dependency_without_an_alias() {
+        let package_directory: PackageDirectory = serde_json::from_str(
+            r#"{ "path": "force-app", "package": "App", "versionNumber": "1.0.0.NEXT", "dependencies": [{ "package": "A" }] }"#,
+        )
+        .unwrap();
+        let error = Package::from(&package_directory, None).unwrap_err();
+        assert_eq!(
+            "dependency A of package App is not in packageAliases",
+            error.to_string()
+        );
+    }
 
-        assert_eq!("B@2.0", project_config.get_packages()[1].name);
-        // assert_eq!("", todo!());
-        // assert_eq!("04tB00000000000001", todo!());
+    #[test]
+    fn it_should_keep_the_build_number_when_setting_the_version() {
+        let mut project_config = read(Some(String::from(
+            "tests/resources/sfdx-project-valid.json",
+        )))
+        .unwrap();
+        let package = project_config.get_package("Expense Manager").unwrap();
+        package.set_version(&Version::parse("3.3.0").unwrap());
+        assert_eq!("3.3.0.NEXT", package.version_number);
     }
 
     #[test]
-    #[should_panic(expected = "Did not find sfdx-project.json")]
-    fn it_should_not_find_sfdx_project_json() {
-        read(Some(String::from("tests/resources/sfdx-project.json2")));
+    fn it_should_parse_partial_version_numbers() {
+        assert_eq!("1.2.0", Version::parse("1.2.0.NEXT").unwrap().to_string());
+        assert_eq!("2.0.0", Version::parse("2.0").unwrap().to_string());
+        assert_eq!("3.0.0", Version::parse("3").unwrap().to_string());
+        assert!(Version::parse("1.x.0").is_none());
+        assert!(Version::parse("").is_none());
+    }
+
+    #[test]
+    fn it_should_compare_versions() {
+        assert!(Version::parse("2.0.0")
+            .unwrap()
+            .is_higher_than(&Version::parse("1.5.0").unwrap()));
+        assert!(!Version::parse("1.5.0")
+            .unwrap()
+            .is_higher_than(&Version::parse("2.0.0").unwrap()));
+        assert!(Version::parse("1.2.1")
+            .unwrap()
+            .is_higher_than(&Version::parse("1.2.0").unwrap()));
+        assert!(!Version::parse("1.2.0")
+            .unwrap()
+            .is_higher_than(&Version::parse("1.2.0").unwrap()));
+    }
+
+    #[test]
+    fn it_should_find_the_project_root_in_a_parent_directory() {
+        let root = Path::new("tests/resources/fake-salesforce-project");
+        assert_eq!(
+            root,
+            find_root(&root.join("force-app/main/default")).unwrap()
+        );
+        assert_eq!(root, find_root(root).unwrap());
+
+        let dir = std::env::temp_dir().join(format!("ht-no-project-{}", rand::random::<u64>()));
+        fs::create_dir_all(&dir).unwrap();
+        assert!(matches!(
+            find_root(&dir).unwrap_err(),
+            ConfigError::NoProject { .. }
+        ));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn it_should_not_read_malformed_project_json() {
+        let error = read(Some(String::from("tests/resources/sfdx-project-lint.json"))).unwrap_err();
+        assert!(matches!(error, ConfigError::Invalid { .. }));
+        assert!(error
+            .to_string()
+            .contains("$.packageDirectories[0].versionNumber: package Lint has no versionNumber"));
+
+        let error = read(Some(String::from(
+            "tests/resources/malformed/no-aliases.json",
+        )))
+        .unwrap_err();
+        assert!(matches!(error, ConfigError::MissingAlias { .. }));
+        assert!(error
+            .to_string()
+            .contains("dependency A of package App is not in packageAliases"));
+
+        let error = read(Some(String::from(
+            "tests/resources/malformed/truncated.json",
+        )))
+        .unwrap_err();
+        assert!(matches!(error, ConfigError::Malformed { .. }));
+
+        let error = read(Some(String::from("tests/resources/sfdx-project.json2"))).unwrap_err();
+        assert_eq!(
+            "did not find tests/resources/sfdx-project.json2",
+            error.to_string()
+        );
     }
 }
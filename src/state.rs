@@ -0,0 +1,158 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const STATE_DIR: &str = ".ht";
+const STATE_FILE: &str = "state.json";
+
+// local state that has to survive between ht runs, kept out of version control
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct State {
+    #[serde(skip)]
+    dir: PathBuf,
+    #[serde(default)]
+    validations: BTreeMap<String, Validation>,
+    // scratch orgs that were kept after verify, by alias
+    #[serde(default)]
+    scratch_orgs: BTreeMap<String, ScratchOrg>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Validation {
+    pub job_id: String,
+    pub validated_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ScratchOrg {
+    pub username: String,
+    pub devhub: String,
+    pub kept_at: u64,
+}
+
+impl State {
+    pub fn read() -> Result<State> {
+        Self::read_from(Path::new(STATE_DIR))
+    }
+
+    fn read_from(dir: &Path) -> Result<State> {
+        let path = dir.join(STATE_FILE);
+        let mut state = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(path)?)?
+        } else {
+            State::default()
+        };
+        state.dir = dir.to_path_buf();
+        Ok(state)
+    }
+
+    pub fn write(&self) -> Result<()> {
+        create_dir(&self.dir)?;
+        fs::write(
+            self.dir.join(STATE_FILE),
+            serde_json::to_string_pretty(self)?,
+        )?;
+        Ok(())
+    }
+
+    pub fn get_validation(&self, target_org: &str) -> Option<&Validation> {
+        self.validations.get(target_org)
+    }
+
+    pub fn set_validation(&mut self, target_org: &str, job_id: &str) {
+        self.validations.insert(
+            target_org.to_owned(),
+            Validation {
+                job_id: job_id.to_owned(),
+                validated_at: now(),
+            },
+        );
+    }
+
+    pub fn remove_validation(&mut self, target_org: &str) -> Option<Validation> {
+        self.validations.remove(target_org)
+    }
+
+    pub fn get_scratch_orgs(&self) -> &BTreeMap<String, ScratchOrg> {
+        &self.scratch_orgs
+    }
+
+    pub fn add_scratch_org(&mut self, alias: &str, username: &str, devhub: &str) {
+        self.scratch_orgs.insert(
+            alias.to_owned(),
+            ScratchOrg {
+                username: username.to_owned(),
+                devhub: devhub.to_owned(),
+                kept_at: now(),
+            },
+        );
+    }
+
+    pub fn remove_scratch_org(&mut self, alias: &str) -> Option<ScratchOrg> {
+        self.scratch_orgs.remove(alias)
+    }
+}
+
+// a directory under .ht for files generated by a run, e.g. manifests and logs
+pub fn get_dir(name: &str) -> Result<PathBuf> {
+    create_dir(Path::new(STATE_DIR))?;
+    let dir = Path::new(STATE_DIR).join(name);
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+fn create_dir(dir: &Path) -> Result<()> {
+    if !dir.exists() {
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join(".gitignore"), "*\n")?;
+    }
+    Ok(())
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_round_trip_validations() {
+        let dir = std::env::temp_dir().join(format!("ht-state-{}", rand::random::<u64>()));
+
+        let mut state = State::read_from(&dir).unwrap();
+        assert!(state.get_validation("uat").is_none());
+        state.set_validation("uat", "0AfRt00000PqprFKAR");
+        state.write().unwrap();
+
+        let mut state = State::read_from(&dir).unwrap();
+        assert_eq!(
+            "0AfRt00000PqprFKAR",
+            state.get_validation("uat").unwrap().job_id
+        );
+        assert!(state.remove_validation("uat").is_some());
+
+        state.add_scratch_org("project123", "test@example.com", "DevHub");
+        state.write().unwrap();
+        let state = State::read_from(&dir).unwrap();
+        assert_eq!(
+            "test@example.com",
+            state.scratch_orgs["project123"].username
+        );
+        assert!(dir.join(".gitignore").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
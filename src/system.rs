@@ -1,8 +1,102 @@
-use std::process::Command;
+use anyhow::{anyhow, Result};
+use log::{info, warn};
+use std::{
+    io::{BufRead, BufReader, Read},
+    process::{Command, Stdio},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
 
-pub fn exec_script(path: &String) {
-    Command::new("sh")
+pub fn exec_script(
+    interpreter: &str,
+    path: &str,
+    timeout: Option<Duration>,
+    env: &[(String, String)],
+) -> Result<()> {
+    let mut child = Command::new(interpreter)
         .arg(path)
+        .envs(env.iter().map(|(key, value)| (key, value)))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
         .spawn()
-        .expect("Could not execute shell script");
+        .map_err(|e| anyhow!("could not execute {} script {}: {}", interpreter, path, e))?;
+
+    let name = path.to_owned();
+    let stdout = child.stdout.take().map(|x| stream_lines(x, &name, false));
+    let stderr = child.stderr.take().map(|x| stream_lines(x, &name, true));
+
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if timeout.is_some_and(|x| started.elapsed() > x) {
+            child.kill()?;
+            child.wait()?;
+            break None;
+        }
+        thread::sleep(Duration::from_millis(100));
+    };
+    // a killed script can leave children holding the pipes open, so only wait on the output of finished ones
+    if status.is_some() {
+        for handle in [stdout, stderr].into_iter().flatten() {
+            let _ = handle.join();
+        }
+    }
+
+    match status {
+        Some(status) if status.success() => Ok(()),
+        Some(status) => Err(anyhow!("script {} exited with {}", path, status)),
+        None => Err(anyhow!(
+            "script {} timed out after {}s",
+            path,
+            timeout.unwrap_or_default().as_secs()
+        )),
+    }
+}
+
+fn stream_lines<R: Read + Send + 'static>(
+    reader: R,
+    name: &str,
+    is_stderr: bool,
+) -> JoinHandle<()> {
+    let name = name.to_owned();
+    thread::spawn(move || {
+        for line in BufReader::new(reader).lines().map_while(Result::ok) {
+            if is_stderr {
+                warn!("[{}] {}", name, line);
+            } else {
+                info!("[{}] {}", name, line);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn write_script(contents: &str) -> String {
+        let path = std::env::temp_dir().join(format!("ht-script-{}.sh", rand::random::<u64>()));
+        fs::write(&path, contents).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn it_should_pass_the_environment_and_check_the_exit_code() {
+        let path = write_script("test \"$HT_PHASE\" = \"pre\"\n");
+        let env = vec![(String::from("HT_PHASE"), String::from("pre"))];
+        assert!(exec_script("sh", &path, None, &env).is_ok());
+        assert!(exec_script("sh", &path, None, &[]).is_err());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn it_should_time_out() {
+        let path = write_script("sleep 5\n");
+        let result = exec_script("sh", &path, Some(Duration::from_millis(200)), &[]);
+        assert!(result.unwrap_err().to_string().contains("timed out"));
+        fs::remove_file(path).unwrap();
+    }
 }
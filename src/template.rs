@@ -0,0 +1,81 @@
+use anyhow::{anyhow, Result};
+use std::{collections::BTreeMap, env};
+
+const ENV_PREFIX: &str = "env.";
+
+// replaces {{ name }} placeholders with their value, names starting with env. are read from
+// the environment and any undefined name fails the whole render
+pub fn render(template: &str, variables: &BTreeMap<String, String>) -> Result<String> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut undefined = Vec::new();
+
+    for (index, line) in template.split_inclusive('\n').enumerate() {
+        let mut rest = line;
+        while let Some(start) = rest.find("{{") {
+            let Some(end) = rest[start..].find("}}") else {
+                break;
+            };
+            let name = rest[start + 2..start + end].trim();
+            rendered.push_str(&rest[..start]);
+            match get_value(name, variables) {
+                Some(value) => rendered.push_str(&value),
+                None => undefined.push(format!("{} (line {})", name, index + 1)),
+            }
+            rest = &rest[start + end + 2..];
+        }
+        rendered.push_str(rest);
+    }
+
+    if undefined.is_empty() {
+        Ok(rendered)
+    } else {
+        Err(anyhow!(
+            "undefined template variables: {}",
+            undefined.join(", ")
+        ))
+    }
+}
+
+fn get_value(name: &str, variables: &BTreeMap<String, String>) -> Option<String> {
+    match name.strip_prefix(ENV_PREFIX) {
+        Some(env_name) => env::var(env_name).ok(),
+        None => variables.get(name).cloned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_render_variables() {
+        let variables = BTreeMap::from([
+            (String::from("target_org"), String::from("scratch")),
+            (
+                String::from("vars.admin"),
+                String::from("admin@example.com"),
+            ),
+        ]);
+        let template = "// {{ target_org }}\nUser u = [SELECT Id FROM User WHERE Username = '{{vars.admin}}'];\nString path = '{{ env.PATH }}';\n";
+        assert_eq!(
+            format!(
+                "// scratch\nUser u = [SELECT Id FROM User WHERE Username = 'admin@example.com'];\nString path = '{}';\n",
+                env::var("PATH").unwrap()
+            ),
+            render(template, &variables).unwrap()
+        );
+    }
+
+    #[test]
+    fn it_should_fail_on_undefined_variables() {
+        let error = render(
+            "Id a = '{{ missing }}';\nId b = '{{ env.HT_SURELY_NOT_SET }}';",
+            &BTreeMap::new(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            "undefined template variables: missing (line 1), env.HT_SURELY_NOT_SET (line 2)",
+            error.to_string()
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use anyhow::{anyhow, Result};
+use log::info;
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+
+use crate::cli::sf::{CliResult, SalesforceCli};
+use crate::config::UserDefinition;
+use crate::state;
+
+const CREDENTIALS_FILE: &str = "users.json";
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedUser {
+    pub alias: String,
+    pub username: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    password: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instance_url: Option<String>,
+}
+
+pub fn create(cli: &mut SalesforceCli, definitions: &[UserDefinition]) -> Result<Vec<CreatedUser>> {
+    let mut users = Vec::new();
+    for definition in definitions {
+        let command_output = cli.create_user(&definition.alias, &get_fields(definition))?;
+        let username = match &command_output.result {
+            Some(CliResult::CreateUser { fields, .. }) => match fields.get("username") {
+                Some(Value::String(x)) => x.clone(),
+                _ => {
+                    return Err(anyhow!(
+                        "sf did not return a username for {}",
+                        definition.alias
+                    ))
+                }
+            },
+            _ => {
+                return Err(anyhow!(
+                    "sf did not return a username for {}",
+                    definition.alias
+                ))
+            }
+        };
+        info!("Created user {} as {}", username, definition.alias);
+
+        let mut user = CreatedUser {
+            alias: definition.alias.clone(),
+            username,
+            password: None,
+            instance_url: None,
+        };
+        if definition.save_credentials {
+            if let Some(CliResult::DisplayUser {
+                password,
+                instance_url,
+                ..
+            }) = cli.display_user(&definition.alias)?.result
+            {
+                user.password = password;
+                user.instance_url = Some(instance_url);
+            }
+        }
+        users.push(user);
+    }
+
+    let credentials = users
+        .iter()
+        .filter(|x| x.password.is_some())
+        .collect::<Vec<_>>();
+    if !credentials.is_empty() {
+        let path = state::get_dir("")?.join(CREDENTIALS_FILE);
+        fs::write(&path, serde_json::to_string_pretty(&credentials)?)?;
+        info!("User credentials written to {}", path.display());
+    }
+    Ok(users)
+}
+
+fn get_fields(definition: &UserDefinition) -> Vec<String> {
+    let mut fields = vec![format!("profileName={}", definition.profile)];
+    if !definition.permission_sets.is_empty() {
+        fields.push(format!("permsets={}", definition.permission_sets.join(",")));
+    }
+    if let Some(locale) = &definition.locale {
+        fields.push(format!("LocaleSidKey={}", locale));
+    }
+    if definition.save_credentials {
+        fields.push(String::from("generatePassword=true"));
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+    use std::path::Path;
+
+    #[test]
+    fn it_should_get_the_user_fields() {
+        let config = config::read(Path::new("tests/resources/ht.toml")).unwrap();
+        assert_eq!(
+            vec![
+                "profileName=Standard User",
+                "permsets=Expense_Approver",
+                "LocaleSidKey=fr_FR",
+                "generatePassword=true"
+            ],
+            get_fields(&config.users[0])
+        );
+    }
+}